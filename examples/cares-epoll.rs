@@ -123,7 +123,15 @@ fn main() {
             data: 0,
         };
         let mut events = [empty_event; 2];
-        let results = epoll_wait(epoll, &mut events, 500)
+        let timeout = match ares_channel.timeout(None) {
+            Some(duration) => {
+                let ms = duration.as_secs() * 1000 +
+                    (duration.subsec_nanos() as u64 + 999_999) / 1_000_000;
+                ms as isize
+            },
+            None => -1,
+        };
+        let results = epoll_wait(epoll, &mut events, timeout)
             .ok()
             .expect("epoll_wait failed");
 
@@ -131,7 +139,7 @@ fn main() {
         match results {
             0 => {
                 // No events - must be a timeout.  Tell c-ares about it.
-                ares_channel.process_fd(c_ares::INVALID_FD, c_ares::INVALID_FD);
+                ares_channel.process_timeouts();
             },
             n => {
                 // Sockets became readable or writable.  Tell c-ares about it.
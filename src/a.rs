@@ -1,63 +1,107 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::borrow::Cow;
 use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::mem;
 use std::net::Ipv4Addr;
 use std::ptr;
 use std::slice;
-use std::str;
 
 use error::AresError;
 use types::hostent;
+use unwind;
 use utils::ares_error;
 
 /// The result of a successful A lookup.
 pub struct AResults {
     hostent: *mut hostent,
+    ttls: Vec<c_ares_sys::Struct_ares_addrttl>,
     phantom: PhantomData<hostent>,
 }
 
 /// The contents of a single A record.
 pub struct AResult<'a> {
     h_addr: *const libc::c_char,
+    ttl: i32,
     phantom: PhantomData<&'a hostent>,
 }
 
+/// An owned copy of an `AResults`, as returned by
+/// `AResults::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedAResults {
+    /// The hostname.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub hostname: String,
+
+    /// The hostname, exactly as received.
+    pub hostname_bytes: Vec<u8>,
+
+    /// The A records.
+    pub results: Vec<OwnedAResult>,
+}
+
+/// An owned copy of a single A record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedAResult {
+    /// The IPv4 address.
+    pub ipv4_addr: Ipv4Addr,
+
+    /// The time-to-live, in seconds.
+    pub ttl: i32,
+}
+
 impl AResults {
     /// Obtain an `AResults` from the response to an A lookup.
     pub fn parse_from(data: &[u8]) -> Result<AResults, AresError> {
         let mut hostent: *mut hostent = ptr::null_mut();
+
+        // Each record takes at least sixteen bytes, so there can't be more
+        // records than this.
+        let max_ttls = data.len() / 16 + 1;
+        let mut ttls =
+            vec![c_ares_sys::Struct_ares_addrttl::default(); max_ttls];
+        let mut nttls = max_ttls as libc::c_int;
         let parse_status = unsafe {
             c_ares_sys::ares_parse_a_reply(
                 data.as_ptr(),
                 data.len() as libc::c_int,
                 &mut hostent
                     as *mut *mut _ as *mut *mut c_ares_sys::Struct_hostent,
-                ptr::null_mut(),
-                ptr::null_mut())
+                ttls.as_mut_ptr(),
+                &mut nttls)
         };
         if parse_status != c_ares_sys::ARES_SUCCESS {
             Err(ares_error(parse_status))
         } else {
-            let result = AResults::new(hostent);
+            ttls.truncate(nttls as usize);
+            let result = AResults::new(hostent, ttls);
             Ok(result)
         }
     }
 
-    fn new(hostent: *mut hostent) -> AResults {
+    fn new(
+        hostent: *mut hostent,
+        ttls: Vec<c_ares_sys::Struct_ares_addrttl>) -> AResults {
         AResults {
             hostent: hostent,
+            ttls: ttls,
             phantom: PhantomData,
         }
     }
 
-    /// Returns the hostname from this `AResults`.
-    pub fn hostname(&self) -> &str {
+    /// Returns the hostname from this `AResults`.  Any invalid UTF-8 is
+    /// replaced by U+FFFD.
+    pub fn hostname(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.hostname_bytes())
+    }
+
+    /// Returns the hostname from this `AResults`, exactly as received.
+    pub fn hostname_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr((*self.hostent).h_name);
-            str::from_utf8_unchecked(c_str.to_bytes())
+            c_str.to_bytes()
         }
     }
 
@@ -65,13 +109,24 @@ impl AResults {
     pub fn iter(&self) -> AResultsIterator {
         AResultsIterator {
             next: unsafe { (*self.hostent).h_addr_list as *const *const _ },
+            ttls: self.ttls.iter(),
             phantom: PhantomData,
         }
     }
+
+    /// Returns an owned copy of this `AResults`, which can outlive it.
+    pub fn to_owned(&self) -> OwnedAResults {
+        OwnedAResults {
+            hostname: self.hostname().into_owned(),
+            hostname_bytes: self.hostname_bytes().to_vec(),
+            results: self.iter().map(|result| result.to_owned()).collect(),
+        }
+    }
 }
 
 pub struct AResultsIterator<'a> {
     next: *const *const libc::c_char,
+    ttls: slice::Iter<'a, c_ares_sys::Struct_ares_addrttl>,
     phantom: PhantomData<&'a hostent>,
 }
 
@@ -85,6 +140,7 @@ impl<'a> Iterator for AResultsIterator<'a> {
             self.next = unsafe { self.next.offset(1) };
             let a_result = AResult {
                 h_addr: h_addr,
+                ttl: self.ttls.next().map_or(0, |addrttl| addrttl.ttl),
                 phantom: PhantomData,
             };
             Some(a_result)
@@ -128,6 +184,19 @@ impl<'a> AResult<'a> {
                 *self.h_addr.offset(3) as u8)
         }
     }
+
+    /// Returns the time-to-live in this `AResult`, in seconds.
+    pub fn ttl(&self) -> i32 {
+        self.ttl
+    }
+
+    /// Returns an owned copy of this `AResult`.
+    pub fn to_owned(&self) -> OwnedAResult {
+        OwnedAResult {
+            ipv4_addr: self.ipv4_addr(),
+            ttl: self.ttl(),
+        }
+    }
 }
 
 pub unsafe extern "C" fn query_a_callback<F>(
@@ -137,12 +206,14 @@ pub unsafe extern "C" fn query_a_callback<F>(
     abuf: *mut libc::c_uchar,
     alen: libc::c_int)
     where F: FnOnce(Result<AResults, AresError>) + 'static {
-    let handler: Box<F> = mem::transmute(arg);
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let data = slice::from_raw_parts(abuf, alen as usize);
-        AResults::parse_from(data)
-    };
-    handler(result);
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            AResults::parse_from(data)
+        };
+        handler(result);
+    });
 }
@@ -1,62 +1,106 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::borrow::Cow;
 use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::mem;
 use std::net::Ipv6Addr;
 use std::ptr;
 use std::slice;
-use std::str;
 
 use error::AresError;
 use types::hostent;
+use unwind;
 use utils::ares_error;
 
 /// The result of a successful AAAA lookup.
 pub struct AAAAResults {
     hostent: *mut hostent,
+    ttls: Vec<c_ares_sys::Struct_ares_addr6ttl>,
     phantom: PhantomData<hostent>,
 }
 
 /// The contents of a single AAAA record.
 pub struct AAAAResult<'a> {
     h_addr: *const libc::c_char,
+    ttl: i32,
     phantom: PhantomData<&'a hostent>,
 }
 
+/// An owned copy of an `AAAAResults`, as returned by
+/// `AAAAResults::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedAAAAResults {
+    /// The hostname.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub hostname: String,
+
+    /// The hostname, exactly as received.
+    pub hostname_bytes: Vec<u8>,
+
+    /// The AAAA records.
+    pub results: Vec<OwnedAAAAResult>,
+}
+
+/// An owned copy of a single AAAA record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedAAAAResult {
+    /// The IPv6 address.
+    pub ipv6_addr: Ipv6Addr,
+
+    /// The time-to-live, in seconds.
+    pub ttl: i32,
+}
+
 impl AAAAResults {
     /// Obtain an `AAAAResults` from the response to an AAAA lookup.
     pub fn parse_from(data: &[u8]) -> Result<AAAAResults, AresError> {
         let mut hostent: *mut hostent = ptr::null_mut();
+
+        // Each record takes at least sixteen bytes, so there can't be more
+        // records than this.
+        let max_ttls = data.len() / 16 + 1;
+        let mut ttls =
+            vec![c_ares_sys::Struct_ares_addr6ttl::default(); max_ttls];
+        let mut nttls = max_ttls as libc::c_int;
         let parse_status = unsafe {
             c_ares_sys::ares_parse_aaaa_reply(
                 data.as_ptr(),
                 data.len() as libc::c_int,
                 &mut hostent
                     as *mut *mut _ as *mut *mut c_ares_sys::Struct_hostent,
-                ptr::null_mut(),
-                ptr::null_mut())
+                ttls.as_mut_ptr(),
+                &mut nttls)
         };
         if parse_status != c_ares_sys::ARES_SUCCESS {
             Err(ares_error(parse_status))
         } else {
-            let result = AAAAResults::new(hostent);
+            ttls.truncate(nttls as usize);
+            let result = AAAAResults::new(hostent, ttls);
             Ok(result)
         }
     }
-    fn new(hostent: *mut hostent) -> AAAAResults {
+    fn new(
+        hostent: *mut hostent,
+        ttls: Vec<c_ares_sys::Struct_ares_addr6ttl>) -> AAAAResults {
         AAAAResults {
             hostent: hostent,
+            ttls: ttls,
             phantom: PhantomData,
         }
     }
 
-    /// Returns the hostname from this `AAAAResults`.
-    pub fn hostname(&self) -> &str {
+    /// Returns the hostname from this `AAAAResults`.  Any invalid UTF-8 is
+    /// replaced by U+FFFD.
+    pub fn hostname(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.hostname_bytes())
+    }
+
+    /// Returns the hostname from this `AAAAResults`, exactly as received.
+    pub fn hostname_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr((*self.hostent).h_name);
-            str::from_utf8_unchecked(c_str.to_bytes())
+            c_str.to_bytes()
         }
     }
 
@@ -65,13 +109,24 @@ impl AAAAResults {
     pub fn iter(&self) -> AAAAResultsIterator {
         AAAAResultsIterator {
             next: unsafe { (*self.hostent).h_addr_list as *const *const _ },
+            ttls: self.ttls.iter(),
             phantom: PhantomData,
         }
     }
+
+    /// Returns an owned copy of this `AAAAResults`, which can outlive it.
+    pub fn to_owned(&self) -> OwnedAAAAResults {
+        OwnedAAAAResults {
+            hostname: self.hostname().into_owned(),
+            hostname_bytes: self.hostname_bytes().to_vec(),
+            results: self.iter().map(|result| result.to_owned()).collect(),
+        }
+    }
 }
 
 pub struct AAAAResultsIterator<'a> {
     next: *const *const libc::c_char,
+    ttls: slice::Iter<'a, c_ares_sys::Struct_ares_addr6ttl>,
     phantom: PhantomData<&'a hostent>,
 }
 
@@ -85,6 +140,7 @@ impl<'a> Iterator for AAAAResultsIterator<'a> {
             self.next = unsafe { self.next.offset(1) };
             let aaaa_result = AAAAResult {
                 h_addr: h_addr,
+                ttl: self.ttls.next().map_or(0, |addrttl| addrttl.ttl),
                 phantom: PhantomData,
             };
             Some(aaaa_result)
@@ -133,6 +189,19 @@ impl<'a> AAAAResult<'a> {
                 ((*h_addr.offset(14) as u16) << 8) + *h_addr.offset(15) as u16)
         }
     }
+
+    /// Returns the time-to-live in this `AAAAResult`, in seconds.
+    pub fn ttl(&self) -> i32 {
+        self.ttl
+    }
+
+    /// Returns an owned copy of this `AAAAResult`.
+    pub fn to_owned(&self) -> OwnedAAAAResult {
+        OwnedAAAAResult {
+            ipv6_addr: self.ipv6_addr(),
+            ttl: self.ttl(),
+        }
+    }
 }
 
 pub unsafe extern "C" fn query_aaaa_callback<F>(
@@ -142,12 +211,14 @@ pub unsafe extern "C" fn query_aaaa_callback<F>(
     abuf: *mut libc::c_uchar,
     alen: libc::c_int)
     where F: FnOnce(Result<AAAAResults, AresError>) + 'static {
-    let handler: Box<F> = mem::transmute(arg);
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let data = slice::from_raw_parts(abuf, alen as usize);
-        AAAAResults::parse_from(data)
-    };
-    handler(result);
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            AAAAResults::parse_from(data)
+        };
+        handler(result);
+    });
 }
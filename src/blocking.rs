@@ -0,0 +1,212 @@
+use std::cell::RefCell;
+use std::net::SocketAddr;
+use std::rc::Rc;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use a::AResults;
+use aaaa::AAAAResults;
+use channel::{
+    Channel,
+    Options,
+};
+use cname::CNameResult;
+use error::AresError;
+use handle::QueryHandle;
+use host::OwnedHostResults;
+use mx::MXResults;
+use nameinfo::OwnedNameInfoResult;
+use naptr::NAPTRResults;
+use ni_flags::NIFlags;
+use ns::NSResults;
+use poll::poll_fds;
+use ptr::PTRResults;
+use srv::SRVResults;
+use types::{
+    AddressFamily,
+    INVALID_FD,
+    IpAddr,
+};
+use txt::TXTResults;
+use soa::SOAResult;
+
+// The longest we'll wait for socket events before giving the `Channel` a
+// chance to process timeouts anyway.
+const MAX_WAIT_MS: u64 = 500;
+
+/// A resolver that makes synchronous queries.
+///
+/// A `BlockingResolver` owns a `Channel`.  Each of its query methods makes a
+/// request on that `Channel`, and then listens on the sockets that `c-ares`
+/// asks for - calling `process_fd()` as events happen and timeouts expire -
+/// until the request completes.  Queries that have no method of their own
+/// here can be made with `run()`.
+pub struct BlockingResolver {
+    channel: Channel,
+    query_timeout: Option<Duration>,
+}
+
+impl BlockingResolver {
+    /// Create a new `BlockingResolver`, using the given `Options`.
+    pub fn new(options: Options) -> Result<BlockingResolver, AresError> {
+        Channel::new(options).map(|channel| {
+            BlockingResolver {
+                channel: channel,
+                query_timeout: None,
+            }
+        })
+    }
+
+    /// Give each query made from now on a deadline, `timeout` after it's
+    /// made.  Queries that take longer fail with `ETIMEOUT`.  See
+    /// `Channel::set_query_deadline()`.
+    pub fn set_query_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.query_timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the `Channel` underlying this `BlockingResolver`, for instance
+    /// so that the servers to contact can be configured.
+    pub fn channel(&mut self) -> &mut Channel {
+        &mut self.channel
+    }
+
+    /// Look up the A records associated with `name`.
+    pub fn query_a(&mut self, name: &str) -> Result<AResults, AresError> {
+        self.run(|channel, done| channel.query_a(name, done))
+    }
+
+    /// Look up the AAAA records associated with `name`.
+    pub fn query_aaaa(
+        &mut self,
+        name: &str) -> Result<AAAAResults, AresError> {
+        self.run(|channel, done| channel.query_aaaa(name, done))
+    }
+
+    /// Look up the CNAME record associated with `name`.
+    pub fn query_cname(
+        &mut self,
+        name: &str) -> Result<CNameResult, AresError> {
+        self.run(|channel, done| channel.query_cname(name, done))
+    }
+
+    /// Look up the MX records associated with `name`.
+    pub fn query_mx(&mut self, name: &str) -> Result<MXResults, AresError> {
+        self.run(|channel, done| channel.query_mx(name, done))
+    }
+
+    /// Look up the NAPTR records associated with `name`.
+    pub fn query_naptr(
+        &mut self,
+        name: &str) -> Result<NAPTRResults, AresError> {
+        self.run(|channel, done| channel.query_naptr(name, done))
+    }
+
+    /// Look up the NS records associated with `name`.
+    pub fn query_ns(&mut self, name: &str) -> Result<NSResults, AresError> {
+        self.run(|channel, done| channel.query_ns(name, done))
+    }
+
+    /// Look up the PTR records associated with `name`.
+    pub fn query_ptr(&mut self, name: &str) -> Result<PTRResults, AresError> {
+        self.run(|channel, done| channel.query_ptr(name, done))
+    }
+
+    /// Look up the SRV records associated with `name`.
+    pub fn query_srv(&mut self, name: &str) -> Result<SRVResults, AresError> {
+        self.run(|channel, done| channel.query_srv(name, done))
+    }
+
+    /// Look up the TXT records associated with `name`.
+    pub fn query_txt(&mut self, name: &str) -> Result<TXTResults, AresError> {
+        self.run(|channel, done| channel.query_txt(name, done))
+    }
+
+    /// Look up the SOA record associated with `name`.
+    pub fn query_soa(&mut self, name: &str) -> Result<SOAResult, AresError> {
+        self.run(|channel, done| channel.query_soa(name, done))
+    }
+
+    /// Perform a host query by address.
+    pub fn get_host_by_address(
+        &mut self,
+        address: &IpAddr) -> Result<OwnedHostResults, AresError> {
+        self.run(|channel, done| {
+            channel.get_host_by_address(address, move |result| {
+                done(result.map(|results| results.to_owned()))
+            })
+        })
+    }
+
+    /// Perform a host query by name.
+    pub fn get_host_by_name(
+        &mut self,
+        name: &str,
+        family: AddressFamily) -> Result<OwnedHostResults, AresError> {
+        self.run(|channel, done| {
+            channel.get_host_by_name(name, family, move |result| {
+                done(result.map(|results| results.to_owned()))
+            })
+        })
+    }
+
+    /// Address-to-nodename translation in protocol-independent manner.
+    pub fn get_name_info(
+        &mut self,
+        address: &SocketAddr,
+        flags: NIFlags) -> Result<OwnedNameInfoResult, AresError> {
+        self.run(|channel, done| {
+            channel.get_name_info(address, flags, move |result| {
+                done(result.map(|result| result.to_owned()))
+            })
+        })
+    }
+
+    /// Make any query that the `Channel` supports, and wait for its result.
+    ///
+    /// `query` is passed the `Channel` and a handler, and should make the
+    /// query with that handler - converting the result to something that
+    /// outlives the handler, if need be.  For instance, `run(|channel, done|
+    /// channel.search_caa(name, done))`, or for a raw query `run(|channel,
+    /// done| channel.query_raw(name, class, query_type, move |result|
+    /// done(result.map(|data| data.to_vec()))))`.
+    pub fn run<R, Q>(&mut self, query: Q) -> R
+        where R: 'static,
+              Q: FnOnce(&mut Channel, Box<FnOnce(R)>) -> QueryHandle {
+        let result = Rc::new(RefCell::new(None));
+        let result_clone = result.clone();
+        let done = Box::new(move |r| {
+            *result_clone.borrow_mut() = Some(r);
+        });
+        let handle = query(&mut self.channel, done);
+        if let Some(timeout) = self.query_timeout {
+            self.channel.set_query_deadline(&handle, Instant::now() + timeout);
+        }
+        loop {
+            if let Some(r) = result.borrow_mut().take() {
+                return r
+            }
+            self.wait_and_process();
+        }
+    }
+
+    // Wait for something to happen on the sockets that c-ares is interested
+    // in, or for a timeout to expire, and tell the `Channel` about it.
+    fn wait_and_process(&mut self) {
+        let sockets: Vec<_> = self.channel.get_sock().iter().collect();
+        let max_wait = Duration::from_millis(MAX_WAIT_MS);
+        let timeout = self.channel.timeout(Some(max_wait));
+        let events = poll_fds(&sockets, timeout);
+        if events.is_empty() {
+            self.channel.process_timeouts();
+        } else {
+            for (fd, readable, writable) in events {
+                let read_fd = if readable { fd } else { INVALID_FD };
+                let write_fd = if writable { fd } else { INVALID_FD };
+                self.channel.process_fd(read_fd, write_fd);
+            }
+        }
+    }
+}
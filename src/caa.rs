@@ -0,0 +1,238 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+use std::str;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful CAA lookup.
+pub struct CAAResults {
+    caa_records: Vec<CAARecord>,
+}
+
+/// The contents of a single CAA record.
+pub struct CAAResult<'a> {
+    // A single result - reference into a `CAAResults`.
+    caa_record: &'a CAARecord,
+}
+
+/// An owned copy of a single CAA record, as returned by
+/// `CAAResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedCAAResult {
+    /// The flags.
+    pub flags: u8,
+
+    /// The property tag.
+    pub tag: String,
+
+    /// The property value.
+    pub value: Vec<u8>,
+}
+
+// c-ares doesn't (in all versions that we support) know how to parse CAA
+// records, so we do it ourselves.
+struct CAARecord {
+    flags: u8,
+    tag: String,
+    value: Vec<u8>,
+}
+
+impl CAAResults {
+    /// Obtain a `CAAResults` from the response to a CAA lookup.
+    pub fn parse_from(data: &[u8]) -> Result<CAAResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut caa_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::CAA)? {
+            let mut reader = Reader::new(rr.rdata());
+            let flags = reader.read_u8()?;
+            let tag_length = reader.read_u8()?;
+            let tag = reader.read_bytes(tag_length as usize)?;
+            if tag.is_empty() || !tag.iter().all(u8::is_ascii_alphanumeric) {
+                return Err(AresError::EBADRESP)
+            }
+            let caa_record = CAARecord {
+                flags: flags,
+                tag: str::from_utf8(tag).unwrap().to_owned(),
+                value: reader.read_rest().to_vec(),
+            };
+            caa_records.push(caa_record);
+        }
+        let results = CAAResults {
+            caa_records: caa_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `CAAResult` values in this `CAAResults`.
+    pub fn iter(&self) -> CAAResultsIterator {
+        CAAResultsIterator {
+            next: self.caa_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `CAAResult` values in this `CAAResults`,
+    /// which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedCAAResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct CAAResultsIterator<'a> {
+    next: slice::Iter<'a, CAARecord>,
+}
+
+impl<'a> Iterator for CAAResultsIterator<'a> {
+    type Item = CAAResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|caa_record| {
+            CAAResult {
+                caa_record: caa_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a CAAResults {
+    type Item = CAAResult<'a>;
+    type IntoIter = CAAResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> CAAResult<'a> {
+    /// Returns whether the issuer critical flag is set in this `CAAResult`.
+    /// A certificate authority that doesn't understand the property tag must
+    /// not issue if this is set.
+    pub fn critical(&self) -> bool {
+        self.caa_record.flags & 0x80 != 0
+    }
+
+    /// Returns the flags from this `CAAResult`.
+    pub fn flags(&self) -> u8 {
+        self.caa_record.flags
+    }
+
+    /// Returns the property tag from this `CAAResult` - for instance "issue",
+    /// "issuewild" or "iodef".
+    pub fn tag(&self) -> &str {
+        &self.caa_record.tag
+    }
+
+    /// Returns the property value from this `CAAResult`.
+    pub fn value(&self) -> &[u8] {
+        &self.caa_record.value
+    }
+
+    /// Returns an owned copy of this `CAAResult`.
+    pub fn to_owned(&self) -> OwnedCAAResult {
+        OwnedCAAResult {
+            flags: self.flags(),
+            tag: self.tag().to_owned(),
+            value: self.value().to_vec(),
+        }
+    }
+}
+
+pub unsafe extern "C" fn query_caa_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<CAAResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            CAAResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use error::AresError;
+    use message::{
+        assert_rejects_overlong_rdata,
+        assert_rejects_rdata,
+        parse_test_response,
+        test_response,
+    };
+    use types::QueryType;
+    use super::CAAResults;
+
+    #[test]
+    fn parses_records() {
+        let results = parse_test_response(
+            CAAResults::parse_from,
+            QueryType::CAA,
+            &[
+            b"\x00\x05issueca.example.net",
+            b"\x80\x05iodefmailto:security@example.com"]).unwrap();
+        let results: Vec<_> = results.iter().map(|r| r.to_owned()).collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].flags, 0);
+        assert_eq!(results[0].tag, "issue");
+        assert_eq!(results[0].value, b"ca.example.net");
+        assert_eq!(results[1].flags, 0x80);
+        assert_eq!(results[1].tag, "iodef");
+        assert_eq!(results[1].value, b"mailto:security@example.com");
+
+        let results = parse_test_response(
+            CAAResults::parse_from,
+            QueryType::CAA,
+            &[b"\x80\x09issuewild;"]).unwrap();
+        let result = results.iter().next().unwrap();
+        assert!(result.critical());
+        assert_eq!(result.tag(), "issuewild");
+        assert_eq!(result.value(), b";");
+    }
+
+    #[test]
+    fn rejects_bad_tags() {
+        assert_rejects_rdata(CAAResults::parse_from, QueryType::CAA, &[
+            b"\x00\x00value",
+            b"\x00\x05is-uevalue",
+            b"\x00\x05iss\xc3\xa9value"]);
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(CAAResults::parse_from, QueryType::CAA, &[
+            b"",
+            b"\x00",
+            b"\x00\x05iss"]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        assert_rejects_overlong_rdata(
+            CAAResults::parse_from,
+            QueryType::CAA,
+            b"\x00\x05issue");
+    }
+
+    #[test]
+    fn needs_caa_answers() {
+        let data = test_response(QueryType::TXT, &[b"\x05hello"]);
+        match CAAResults::parse_from(&data) {
+            Err(AresError::ENODATA) => (),
+            _ => panic!("unexpected result"),
+        }
+    }
+}
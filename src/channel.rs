@@ -1,7 +1,14 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::any::Any;
+use std::cmp;
+use std::collections::hash_map::RandomState;
 use std::ffi::CString;
+use std::hash::{
+    BuildHasher,
+    Hasher,
+};
 use std::marker::PhantomData;
 use std::mem;
 use std::net::{
@@ -10,7 +17,13 @@ use std::net::{
     SocketAddr,
 };
 use std::os::unix::io;
+use std::panic;
 use std::ptr;
+use std::sync::Weak;
+use std::time::{
+    Duration,
+    Instant,
+};
 
 use a::{
     AResults,
@@ -20,16 +33,45 @@ use aaaa::{
     AAAAResults,
     query_aaaa_callback,
 };
+use caa::{
+    CAAResults,
+    query_caa_callback,
+};
 use cname::{
     CNameResult,
     query_cname_callback,
 };
+use dnskey::{
+    DNSKEYResults,
+    query_dnskey_callback,
+};
+use ds::{
+    DSResults,
+    query_ds_callback,
+};
 use error::AresError;
 use flags::Flags;
+use hinfo::{
+    HINFOResults,
+    query_hinfo_callback,
+};
+use handle::{
+    Finish,
+    QueryCallback,
+    QueryHandle,
+    tracked_host_callback,
+    tracked_name_info_callback,
+    tracked_query_callback,
+};
 use host::{
     HostResults,
     get_host_callback,
 };
+use loc::{
+    LOCResults,
+    query_loc_callback,
+};
+use message::MessageBuilder;
 use mx::{
     MXResults,
     query_mx_callback,
@@ -47,17 +89,48 @@ use ns::{
     NSResults,
     query_ns_callback,
 };
+use nsec::{
+    NSECResults,
+    query_nsec_callback,
+};
+use nsec3::{
+    NSEC3Results,
+    query_nsec3_callback,
+};
 use ptr::{
     PTRResults,
     query_ptr_callback,
 };
+use query::query_callback;
+use rp::{
+    RPResults,
+    query_rp_callback,
+};
+use rrsig::{
+    RRSIGResults,
+    query_rrsig_callback,
+};
 use srv::{
     SRVResults,
     query_srv_callback,
 };
+use sshfp::{
+    SSHFPResults,
+    query_sshfp_callback,
+};
+use svcb::{
+    SVCBResults,
+    query_https_callback,
+    query_svcb_callback,
+};
+use tlsa::{
+    TLSAResults,
+    query_tlsa_callback,
+};
 use types::{
     AddressFamily,
     DnsClass,
+    INVALID_FD,
     IpAddr,
     QueryType,
 };
@@ -69,20 +142,31 @@ use soa::{
     SOAResult,
     query_soa_callback,
 };
+use unwind;
+use uri::{
+    URIResults,
+    query_uri_callback,
+};
 use utils::{
   ares_error,
   ipv4_as_in_addr,
   ipv6_as_in6_addr,
   socket_addrv4_as_sockaddr_in,
   socket_addrv6_as_sockaddr_in6,
+  to_c_name,
+  to_c_string,
 };
 
+// The EDNS payload size that c-ares advertises by default.
+const DEFAULT_EDNS_PAYLOAD_SIZE: u16 = 1280;
+
 /// Used to configure the behaviour of the name resolver.
 pub struct Options {
     ares_options: c_ares_sys::Struct_ares_options,
     optmask: libc::c_int,
     domains: Vec<CString>,
     lookups: Option<CString>,
+    dnssec_ok: bool,
     socket_state_callback: Option<Box<FnMut(io::RawFd, bool, bool) + 'static>>,
 }
 
@@ -94,6 +178,7 @@ impl Options {
             optmask: 0,
             domains: Vec::new(),
             lookups: None,
+            dnssec_ok: false,
             socket_state_callback: None,
         }
     }
@@ -152,23 +237,31 @@ impl Options {
 
     /// Set the domains to search, instead of the domains specified in
     /// resolv.conf or the domain derived from the kernel hostname variable.
-    pub fn set_domains(&mut self, domains: &[&str]) -> &mut Self {
+    ///
+    /// Fails with `EBADNAME` if any of the domains is not a valid name.
+    pub fn set_domains(
+        &mut self,
+        domains: &[&str]) -> Result<&mut Self, AresError> {
         self.domains = domains
             .iter()
-            .map(|&s| CString::new(s).unwrap())
-            .collect();
+            .map(|&s| to_c_name(s))
+            .collect::<Result<Vec<_>, _>>()?;
         self.optmask = self.optmask | c_ares_sys::ARES_OPT_DOMAINS;
-        self
+        Ok(self)
     }
 
     /// Set the lookups to perform for host queries. `lookups` should be set to
     /// a string of the characters "b" or "f", where "b" indicates a DNS lookup
     /// and "f" indicates a lookup in the hosts file.
-    pub fn set_lookups(&mut self, lookups: &str) -> &mut Self {
-        let c_lookups = CString::new(lookups).unwrap();
+    ///
+    /// Fails with `EBADSTR` if `lookups` contains a NUL.
+    pub fn set_lookups(
+        &mut self,
+        lookups: &str) -> Result<&mut Self, AresError> {
+        let c_lookups = to_c_string(lookups)?;
         self.lookups = Some(c_lookups);
         self.optmask = self.optmask | c_ares_sys::ARES_OPT_LOOKUPS;
-        self
+        Ok(self)
     }
 
     /// Set the callback function to be invoked when a socket changes state.
@@ -215,13 +308,43 @@ impl Options {
         self.optmask = self.optmask | c_ares_sys::ARES_OPT_EDNSPSZ;
         self
     }
+
+    /// Set the DNSSEC OK bit, in an EDNS record, on queries for DNSSEC
+    /// records - so that servers include signatures in their responses.
+    ///
+    /// `c-ares` can't do this itself, so such queries are built by this crate
+    /// and sent with `ares_send()`.  This means that they don't use the
+    /// configured search domains.
+    pub fn set_dnssec_ok(&mut self) -> &mut Self {
+        self.dnssec_ok = true;
+        self
+    }
 }
 
 /// A channel for name service lookups.
+///
+/// Names passed to the lookup methods are checked before any query is sent.
+/// If a name contains a NUL character, or breaks the limits on the lengths of
+/// names and labels, then the handler is called straight away with
+/// `EBADNAME`.
+///
+/// Each lookup method returns a `QueryHandle`, which can be used to cancel
+/// that query alone.
 pub struct Channel {
     ares_channel: c_ares_sys::ares_channel,
     phantom: PhantomData<c_ares_sys::Struct_ares_channeldata>,
 
+    // How to build queries that c-ares can't build for us.
+    dnssec_ok: bool,
+    recursion_desired: bool,
+    edns_payload_size: u16,
+
+    // A panic from a callback, waiting to be re-raised.
+    panic: Option<Box<Any + Send>>,
+
+    // Queries that have their own deadlines.
+    deadlines: Vec<(Instant, Weak<Finish>)>,
+
     // For ownership only.
     #[allow(dead_code)]
     socket_state_callback: Option<Box<FnMut(io::RawFd, bool, bool) + 'static>>,
@@ -266,9 +389,23 @@ impl Channel {
             return Err(ares_error(channel_rc))
         }
 
+        let optmask = options.optmask;
+        let flags = options.ares_options.flags;
+        let recursion_desired = optmask & c_ares_sys::ARES_OPT_FLAGS == 0 ||
+            flags & c_ares_sys::ARES_FLAG_NORECURSE == 0;
+        let edns_payload_size = if optmask & c_ares_sys::ARES_OPT_EDNSPSZ != 0 {
+            options.ares_options.ednspsz as u16
+        } else {
+            DEFAULT_EDNS_PAYLOAD_SIZE
+        };
         let channel = Channel {
             ares_channel: ares_channel,
             phantom: PhantomData,
+            dnssec_ok: options.dnssec_ok,
+            recursion_desired: recursion_desired,
+            edns_payload_size: edns_payload_size,
+            panic: None,
+            deadlines: Vec::new(),
             socket_state_callback: options.socket_state_callback,
         };
         Ok(channel)
@@ -280,6 +417,10 @@ impl Channel {
     /// Providing a value for `read_fd` indicates that the identified socket
     /// is readable; likewise providing a value for `write_fd` indicates that
     /// the identified socket is writable.  Use `INVALID_FD` for "no action".
+    ///
+    /// If a handler or callback panicked since the last call to `process_fd()`
+    /// then the panic is resumed here.  Panics are not allowed to unwind
+    /// through `c-ares` itself.
     pub fn process_fd(&mut self, read_fd: io::RawFd, write_fd: io::RawFd) {
         unsafe {
             c_ares_sys::ares_process_fd(
@@ -287,6 +428,16 @@ impl Channel {
                 read_fd as c_ares_sys::ares_socket_t,
                 write_fd as c_ares_sys::ares_socket_t);
         }
+        self.expire_deadlines();
+        self.resume_panic();
+    }
+
+    /// Give `c-ares` the chance to handle any queries that have timed out.
+    ///
+    /// This is equivalent to calling `process_fd()` with `INVALID_FD` for both
+    /// file descriptors.  Use `timeout()` to find out when it's needed.
+    pub fn process_timeouts(&mut self) {
+        self.process_fd(INVALID_FD, INVALID_FD);
     }
 
     /// Retrieve the set of socket descriptors which the calling application
@@ -302,16 +453,78 @@ impl Channel {
         GetSock::new(socks, bitmask as u32)
     }
 
+    /// Returns how long the caller may wait for socket events before it must
+    /// call `process_fd()` or `process_timeouts()`, so that `c-ares` can handle
+    /// queries that have timed out.  This takes into account any deadlines set
+    /// with `set_query_deadline()`.
+    ///
+    /// If `max` is provided, the value returned is no greater than `max`.
+    /// `None` is returned only if there is no limit: that is, if `max` is
+    /// `None` and there are no queries pending.
+    pub fn timeout(&self, max: Option<Duration>) -> Option<Duration> {
+        let max = match (max, self.next_deadline()) {
+            (Some(max), Some(deadline)) => Some(cmp::min(max, deadline)),
+            (max, deadline) => max.or(deadline),
+        };
+        let mut maxtv = max.map(|duration| {
+            libc::timeval {
+                tv_sec: duration.as_secs() as libc::time_t,
+                tv_usec: (duration.subsec_nanos() / 1000)
+                    as libc::suseconds_t,
+            }
+        });
+        let maxtv_ptr = match maxtv {
+            Some(ref mut tv) => tv as *mut libc::timeval,
+            None => ptr::null_mut(),
+        };
+        let mut tv: libc::timeval = unsafe { mem::zeroed() };
+        let tv_ptr = unsafe {
+            c_ares_sys::ares_timeout(self.ares_channel, maxtv_ptr, &mut tv)
+        };
+        if tv_ptr.is_null() {
+            None
+        } else {
+            let tv = unsafe { *tv_ptr };
+            let duration = Duration::new(
+                tv.tv_sec as u64,
+                tv.tv_usec as u32 * 1000);
+            Some(duration)
+        }
+    }
+
+    /// Set a deadline for a query made on this `Channel`.  If the query hasn't
+    /// completed by then, its handler is called with `ETIMEOUT` - whatever
+    /// the timeout and number of tries configured for the channel.  Other
+    /// queries are not affected.
+    ///
+    /// `c-ares` can't abandon a single query, so the query itself keeps
+    /// running - and keeps its sockets open - until it completes or times out
+    /// as usual.  Only its result is discarded.
+    ///
+    /// The deadline is enforced by `process_fd()` and `process_timeouts()`.
+    pub fn set_query_deadline(
+        &mut self,
+        query: &QueryHandle,
+        deadline: Instant) -> &mut Self {
+        self.deadlines.push((deadline, query.downgrade()));
+        self
+    }
+
     /// Set the list of servers to contact, instead of the servers specified
     /// in resolv.conf or the local named.
     ///
     /// String format is `host[:port]`.  IPv6 addresses with ports require
     /// square brackets eg `[2001:4860:4860::8888]:53`.
+    ///
+    /// Fails with `EBADSTR` if any of the servers contains a NUL or a comma.
     pub fn set_servers(
         &mut self,
         servers: &[&str]) -> Result<&mut Self, AresError> {
+        if servers.iter().any(|server| server.contains(',')) {
+            return Err(AresError::EBADSTR)
+        }
         let servers_csv = servers.connect(",");
-        let c_servers = CString::new(servers_csv).unwrap();
+        let c_servers = to_c_string(&servers_csv)?;
         let ares_rc = unsafe {
             c_ares_sys::ares_set_servers_csv(
                 self.ares_channel,
@@ -343,192 +556,695 @@ impl Channel {
     }
 
     /// Set the local device from which to make queries.
-    pub fn set_local_device(&mut self, device: &str) -> &mut Self {
-        let c_dev = CString::new(device).unwrap();
+    ///
+    /// Fails with `EBADSTR` if `device` contains a NUL.
+    pub fn set_local_device(
+        &mut self,
+        device: &str) -> Result<&mut Self, AresError> {
+        let c_dev = to_c_string(device)?;
         unsafe {
             c_ares_sys::ares_set_local_dev(self.ares_channel, c_dev.as_ptr());
         }
-        self
+        Ok(self)
     }
 
     /// Look up the A records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_a<F>(&mut self, name: &str, handler: F)
+    pub fn query_a<F>(&mut self, name: &str, handler: F) -> QueryHandle
         where F: FnOnce(Result<AResults, AresError>) + 'static {
-        let c_name = CString::new(name).unwrap();
-        unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
-            c_ares_sys::ares_query(
-                self.ares_channel,
-                c_name.as_ptr(),
-                DnsClass::IN as libc::c_int,
-                QueryType::A as libc::c_int,
-                Some(query_a_callback::<F>),
-                c_arg);
-        }
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::A as u16,
+            false,
+            query_a_callback::<F>,
+            handler)
     }
 
     /// Look up the AAAA records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_aaaa<F>(&mut self, name: &str, handler: F)
+    pub fn query_aaaa<F>(&mut self, name: &str, handler: F) -> QueryHandle
         where F: FnOnce(Result<AAAAResults, AresError>) + 'static {
-        let c_name = CString::new(name).unwrap();
-        unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
-            c_ares_sys::ares_query(
-                self.ares_channel,
-                c_name.as_ptr(),
-                DnsClass::IN as libc::c_int,
-                QueryType::AAAA as libc::c_int,
-                Some(query_aaaa_callback::<F>),
-                c_arg);
-        }
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::AAAA as u16,
+            false,
+            query_aaaa_callback::<F>,
+            handler)
     }
 
     /// Look up the CNAME record associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_cname<F>(&mut self, name: &str, handler: F)
+    pub fn query_cname<F>(&mut self, name: &str, handler: F) -> QueryHandle
         where F: FnOnce(Result<CNameResult, AresError>) + 'static {
-        let c_name = CString::new(name).unwrap();
-        unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
-            c_ares_sys::ares_query(
-                self.ares_channel,
-                c_name.as_ptr(),
-                DnsClass::IN as libc::c_int,
-                QueryType::CNAME as libc::c_int,
-                Some(query_cname_callback::<F>),
-                c_arg);
-        }
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::CNAME as u16,
+            false,
+            query_cname_callback::<F>,
+            handler)
     }
 
     /// Look up the MX records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_mx<F>(&mut self, name: &str, handler: F)
+    pub fn query_mx<F>(&mut self, name: &str, handler: F) -> QueryHandle
         where F: FnOnce(Result<MXResults, AresError>) + 'static {
-        let c_name = CString::new(name).unwrap();
-        unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
-            c_ares_sys::ares_query(
-                self.ares_channel,
-                c_name.as_ptr(),
-                DnsClass::IN as libc::c_int,
-                QueryType::MX as libc::c_int,
-                Some(query_mx_callback::<F>),
-                c_arg);
-        }
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::MX as u16,
+            false,
+            query_mx_callback::<F>,
+            handler)
     }
 
     /// Look up the NAPTR records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_naptr<F>(&mut self, name: &str, handler: F)
+    pub fn query_naptr<F>(&mut self, name: &str, handler: F) -> QueryHandle
         where F: FnOnce(Result<NAPTRResults, AresError>) + 'static {
-        let c_name = CString::new(name).unwrap();
-        unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
-            c_ares_sys::ares_query(
-                self.ares_channel,
-                c_name.as_ptr(),
-                DnsClass::IN as libc::c_int,
-                QueryType::NAPTR as libc::c_int,
-                Some(query_naptr_callback::<F>),
-                c_arg);
-        }
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::NAPTR as u16,
+            false,
+            query_naptr_callback::<F>,
+            handler)
     }
 
     /// Look up the NS records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_ns<F>(&mut self, name: &str, handler: F)
+    pub fn query_ns<F>(&mut self, name: &str, handler: F) -> QueryHandle
         where F: FnOnce(Result<NSResults, AresError>) + 'static {
-        let c_name = CString::new(name).unwrap();
-        unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
-            c_ares_sys::ares_query(
-                self.ares_channel,
-                c_name.as_ptr(),
-                DnsClass::IN as libc::c_int,
-                QueryType::NS as libc::c_int,
-                Some(query_ns_callback::<F>),
-                c_arg);
-        }
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::NS as u16,
+            false,
+            query_ns_callback::<F>,
+            handler)
     }
 
     /// Look up the PTR records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_ptr<F>(&mut self, name: &str, handler: F)
+    pub fn query_ptr<F>(&mut self, name: &str, handler: F) -> QueryHandle
         where F: FnOnce(Result<PTRResults, AresError>) + 'static {
-        let c_name = CString::new(name).unwrap();
-        unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
-            c_ares_sys::ares_query(
-                self.ares_channel,
-                c_name.as_ptr(),
-                DnsClass::IN as libc::c_int,
-                QueryType::PTR as libc::c_int,
-                Some(query_ptr_callback::<F>),
-                c_arg);
-        }
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::PTR as u16,
+            false,
+            query_ptr_callback::<F>,
+            handler)
     }
 
     /// Look up the SRV records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_srv<F>(&mut self, name: &str, handler: F)
+    pub fn query_srv<F>(&mut self, name: &str, handler: F) -> QueryHandle
         where F: FnOnce(Result<SRVResults, AresError>) + 'static {
-        let c_name = CString::new(name).unwrap();
-        unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
-            c_ares_sys::ares_query(
-                self.ares_channel,
-                c_name.as_ptr(),
-                DnsClass::IN as libc::c_int,
-                QueryType::SRV as libc::c_int,
-                Some(query_srv_callback::<F>),
-                c_arg);
-        }
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::SRV as u16,
+            false,
+            query_srv_callback::<F>,
+            handler)
     }
 
     /// Look up the TXT records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_txt<F>(&mut self, name: &str, handler: F)
+    pub fn query_txt<F>(&mut self, name: &str, handler: F) -> QueryHandle
         where F: FnOnce(Result<TXTResults, AresError>) + 'static {
-        let c_name = CString::new(name).unwrap();
-        unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
-            c_ares_sys::ares_query(
-                self.ares_channel,
-                c_name.as_ptr(),
-                DnsClass::IN as libc::c_int,
-                QueryType::TXT as libc::c_int,
-                Some(query_txt_callback::<F>),
-                c_arg);
-        }
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::TXT as u16,
+            false,
+            query_txt_callback::<F>,
+            handler)
     }
 
     /// Look up the SOA records associated with `name`.
     ///
     /// On completion, `handler` is called with the result.
-    pub fn query_soa<F>(&mut self, name: &str, handler: F)
+    pub fn query_soa<F>(&mut self, name: &str, handler: F) -> QueryHandle
         where F: FnOnce(Result<SOAResult, AresError>) + 'static {
-        let c_name = CString::new(name).unwrap();
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::SOA as u16,
+            false,
+            query_soa_callback::<F>,
+            handler)
+    }
+
+    /// Look up the CAA records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_caa<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<CAAResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::CAA as u16,
+            false,
+            query_caa_callback::<F>,
+            handler)
+    }
+
+    /// Look up the TLSA records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_tlsa<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<TLSAResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::TLSA as u16,
+            false,
+            query_tlsa_callback::<F>,
+            handler)
+    }
+
+    /// Look up the SSHFP records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_sshfp<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<SSHFPResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::SSHFP as u16,
+            false,
+            query_sshfp_callback::<F>,
+            handler)
+    }
+
+    /// Look up the SVCB records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_svcb<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<SVCBResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::SVCB as u16,
+            false,
+            query_svcb_callback::<F>,
+            handler)
+    }
+
+    /// Look up the HTTPS records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_https<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<SVCBResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::HTTPS as u16,
+            false,
+            query_https_callback::<F>,
+            handler)
+    }
+
+    /// Look up the DNSKEY records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.  See also
+    /// `Options::set_dnssec_ok()`.
+    pub fn query_dnskey<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<DNSKEYResults, AresError>) + 'static {
+        self.query_dnssec(
+            name,
+            QueryType::DNSKEY,
+            query_dnskey_callback::<F>,
+            handler)
+    }
+
+    /// Look up the DS records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.  See also
+    /// `Options::set_dnssec_ok()`.
+    pub fn query_ds<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<DSResults, AresError>) + 'static {
+        self.query_dnssec(
+            name,
+            QueryType::DS,
+            query_ds_callback::<F>,
+            handler)
+    }
+
+    /// Look up the RRSIG records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.  See also
+    /// `Options::set_dnssec_ok()`.
+    pub fn query_rrsig<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<RRSIGResults, AresError>) + 'static {
+        self.query_dnssec(
+            name,
+            QueryType::RRSIG,
+            query_rrsig_callback::<F>,
+            handler)
+    }
+
+    /// Look up the NSEC records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.  See also
+    /// `Options::set_dnssec_ok()`.
+    pub fn query_nsec<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<NSECResults, AresError>) + 'static {
+        self.query_dnssec(
+            name,
+            QueryType::NSEC,
+            query_nsec_callback::<F>,
+            handler)
+    }
+
+    /// Look up the NSEC3 records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.  See also
+    /// `Options::set_dnssec_ok()`.
+    pub fn query_nsec3<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<NSEC3Results, AresError>) + 'static {
+        self.query_dnssec(
+            name,
+            QueryType::NSEC3,
+            query_nsec3_callback::<F>,
+            handler)
+    }
+
+    /// Look up the URI records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_uri<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<URIResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::URI as u16,
+            false,
+            query_uri_callback::<F>,
+            handler)
+    }
+
+    /// Look up the HINFO records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_hinfo<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<HINFOResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::HINFO as u16,
+            false,
+            query_hinfo_callback::<F>,
+            handler)
+    }
+
+    /// Look up the LOC records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_loc<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<LOCResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::LOC as u16,
+            false,
+            query_loc_callback::<F>,
+            handler)
+    }
+
+    /// Look up the RP records associated with `name`.
+    ///
+    /// On completion, `handler` is called with the result.
+    pub fn query_rp<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<RPResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::RP as u16,
+            false,
+            query_rp_callback::<F>,
+            handler)
+    }
+
+    /// Look up the records of type `query_type` and class `dns_class`
+    /// associated with `name`.
+    ///
+    /// This can be used to make queries that this crate does not otherwise
+    /// support.  The type and class are given as plain numbers, so that any
+    /// may be used: `QueryType` and `DnsClass` name the common ones, eg
+    /// `QueryType::TXT as u16`.  On completion, `handler` is called with the
+    /// raw DNS response, which the caller must parse for itself.
+    pub fn query_raw<F>(
+        &mut self,
+        name: &str,
+        dns_class: u16,
+        query_type: u16,
+        handler: F) -> QueryHandle
+        where F: FnOnce(Result<&[u8], AresError>) + 'static {
+        self.make_query(
+            name,
+            dns_class,
+            query_type,
+            false,
+            query_callback::<F>,
+            handler)
+    }
+
+    /// Search for the A records associated with `name`.
+    ///
+    /// Like `query_a()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_a<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<AResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::A as u16,
+            true,
+            query_a_callback::<F>,
+            handler)
+    }
+
+    /// Search for the AAAA records associated with `name`.
+    ///
+    /// Like `query_aaaa()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_aaaa<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<AAAAResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::AAAA as u16,
+            true,
+            query_aaaa_callback::<F>,
+            handler)
+    }
+
+    /// Search for the CNAME record associated with `name`.
+    ///
+    /// Like `query_cname()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_cname<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<CNameResult, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::CNAME as u16,
+            true,
+            query_cname_callback::<F>,
+            handler)
+    }
+
+    /// Search for the MX records associated with `name`.
+    ///
+    /// Like `query_mx()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_mx<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<MXResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::MX as u16,
+            true,
+            query_mx_callback::<F>,
+            handler)
+    }
+
+    /// Search for the NAPTR records associated with `name`.
+    ///
+    /// Like `query_naptr()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_naptr<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<NAPTRResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::NAPTR as u16,
+            true,
+            query_naptr_callback::<F>,
+            handler)
+    }
+
+    /// Search for the NS records associated with `name`.
+    ///
+    /// Like `query_ns()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_ns<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<NSResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::NS as u16,
+            true,
+            query_ns_callback::<F>,
+            handler)
+    }
+
+    /// Search for the PTR records associated with `name`.
+    ///
+    /// Like `query_ptr()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_ptr<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<PTRResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::PTR as u16,
+            true,
+            query_ptr_callback::<F>,
+            handler)
+    }
+
+    /// Search for the SRV records associated with `name`.
+    ///
+    /// Like `query_srv()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_srv<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<SRVResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::SRV as u16,
+            true,
+            query_srv_callback::<F>,
+            handler)
+    }
+
+    /// Search for the TXT records associated with `name`.
+    ///
+    /// Like `query_txt()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_txt<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<TXTResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::TXT as u16,
+            true,
+            query_txt_callback::<F>,
+            handler)
+    }
+
+    /// Search for the SOA records associated with `name`.
+    ///
+    /// Like `query_soa()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_soa<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<SOAResult, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::SOA as u16,
+            true,
+            query_soa_callback::<F>,
+            handler)
+    }
+
+    /// Search for the CAA records associated with `name`.
+    ///
+    /// Like `query_caa()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_caa<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<CAAResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::CAA as u16,
+            true,
+            query_caa_callback::<F>,
+            handler)
+    }
+
+    /// Search for the TLSA records associated with `name`.
+    ///
+    /// Like `query_tlsa()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_tlsa<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<TLSAResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::TLSA as u16,
+            true,
+            query_tlsa_callback::<F>,
+            handler)
+    }
+
+    /// Search for the SSHFP records associated with `name`.
+    ///
+    /// Like `query_sshfp()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_sshfp<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<SSHFPResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::SSHFP as u16,
+            true,
+            query_sshfp_callback::<F>,
+            handler)
+    }
+
+    /// Search for the SVCB records associated with `name`.
+    ///
+    /// Like `query_svcb()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_svcb<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<SVCBResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::SVCB as u16,
+            true,
+            query_svcb_callback::<F>,
+            handler)
+    }
+
+    /// Search for the HTTPS records associated with `name`.
+    ///
+    /// Like `query_https()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_https<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<SVCBResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::HTTPS as u16,
+            true,
+            query_https_callback::<F>,
+            handler)
+    }
+
+    /// Search for the URI records associated with `name`.
+    ///
+    /// Like `query_uri()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_uri<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<URIResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::URI as u16,
+            true,
+            query_uri_callback::<F>,
+            handler)
+    }
+
+    /// Search for the HINFO records associated with `name`.
+    ///
+    /// Like `query_hinfo()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_hinfo<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<HINFOResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::HINFO as u16,
+            true,
+            query_hinfo_callback::<F>,
+            handler)
+    }
+
+    /// Search for the LOC records associated with `name`.
+    ///
+    /// Like `query_loc()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_loc<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<LOCResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::LOC as u16,
+            true,
+            query_loc_callback::<F>,
+            handler)
+    }
+
+    /// Search for the RP records associated with `name`.
+    ///
+    /// Like `query_rp()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the result.
+    pub fn search_rp<F>(&mut self, name: &str, handler: F) -> QueryHandle
+        where F: FnOnce(Result<RPResults, AresError>) + 'static {
+        self.make_query(
+            name,
+            DnsClass::IN as u16,
+            QueryType::RP as u16,
+            true,
+            query_rp_callback::<F>,
+            handler)
+    }
+
+    /// Search for the records of type `query_type` and class `dns_class`
+    /// associated with `name`.
+    ///
+    /// Like `query_raw()`, but applies the configured search domains.  On
+    /// completion, `handler` is called with the raw DNS response.
+    pub fn search_raw<F>(
+        &mut self,
+        name: &str,
+        dns_class: u16,
+        query_type: u16,
+        handler: F) -> QueryHandle
+        where F: FnOnce(Result<&[u8], AresError>) + 'static {
+        self.make_query(
+            name,
+            dns_class,
+            query_type,
+            true,
+            query_callback::<F>,
+            handler)
+    }
+
+    /// Send a DNS query message, such as one built by `create_query()`.
+    ///
+    /// On completion, `handler` is called with the raw DNS response.
+    pub fn send_raw<F>(&mut self, query: &[u8], handler: F) -> QueryHandle
+        where F: FnOnce(Result<&[u8], AresError>) + 'static {
+        let (handle, c_arg) = QueryHandle::query(handler, query_callback::<F>);
         unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
-            c_ares_sys::ares_query(
+            c_ares_sys::ares_send(
                 self.ares_channel,
-                c_name.as_ptr(),
-                DnsClass::IN as libc::c_int,
-                QueryType::SOA as libc::c_int,
-                Some(query_soa_callback::<F>),
+                query.as_ptr(),
+                query.len() as libc::c_int,
+                Some(tracked_query_callback),
                 c_arg);
         }
+        self.collect_panic();
+        handle
     }
 
     /// Perform a host query by address.
@@ -537,7 +1253,8 @@ impl Channel {
     pub fn get_host_by_address<F>(
         &mut self,
         address: &IpAddr,
-        handler: F) where F: FnOnce(Result<HostResults, AresError>) + 'static {
+        handler: F) -> QueryHandle
+        where F: FnOnce(Result<HostResults, AresError>) + 'static {
         let c_addr = match *address {
             IpAddr::V4(ref v4) => {
                 let in_addr = ipv4_as_in_addr(v4);
@@ -556,16 +1273,19 @@ impl Channel {
                 (AddressFamily::INET6, mem::size_of::<libc::in6_addr>())
             },
         };
+        let (handle, c_arg) =
+            QueryHandle::host(handler, get_host_callback::<F>);
         unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
             c_ares_sys::ares_gethostbyaddr(
                 self.ares_channel,
                 c_addr,
                 length as libc::c_int,
                 family as libc::c_int,
-                Some(get_host_callback::<F>),
+                Some(tracked_host_callback),
                 c_arg);
         }
+        self.collect_panic();
+        handle
     }
 
     /// Perform a host query by name.
@@ -575,17 +1295,29 @@ impl Channel {
         &mut self,
         name: &str,
         family: AddressFamily,
-        handler: F) where F: FnOnce(Result<HostResults, AresError>) + 'static {
-        let c_name = CString::new(name).unwrap();
-        unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
-            c_ares_sys::ares_gethostbyname(
-                self.ares_channel,
-                c_name.as_ptr(),
-                family as libc::c_int,
-                Some(get_host_callback::<F>),
-                c_arg);
+        handler: F) -> QueryHandle
+        where F: FnOnce(Result<HostResults, AresError>) + 'static {
+        let (handle, c_arg) =
+            QueryHandle::host(handler, get_host_callback::<F>);
+        match to_c_name(name) {
+            Ok(c_name) => unsafe {
+                c_ares_sys::ares_gethostbyname(
+                    self.ares_channel,
+                    c_name.as_ptr(),
+                    family as libc::c_int,
+                    Some(tracked_host_callback),
+                    c_arg);
+            },
+            Err(error) => unsafe {
+                tracked_host_callback(
+                    c_arg,
+                    error as libc::c_int,
+                    0,
+                    ptr::null_mut());
+            },
         }
+        self.collect_panic();
+        handle
     }
 
     /// Address-to-nodename translation in protocol-independent manner.
@@ -598,7 +1330,7 @@ impl Channel {
         &mut self,
         address: &SocketAddr,
         flags: NIFlags,
-        handler: F)
+        handler: F) -> QueryHandle
         where F: FnOnce(Result<NameInfoResult, AresError>) + 'static {
         let c_addr = match *address {
             SocketAddr::V4(ref v4) => {
@@ -610,33 +1342,203 @@ impl Channel {
                 &sockaddr as *const _ as *const libc::sockaddr
             },
         };
+        let (handle, c_arg) =
+            QueryHandle::name_info(handler, get_name_info_callback::<F>);
         unsafe {
-            let c_arg: *mut libc::c_void = mem::transmute(Box::new(handler));
             c_ares_sys::ares_getnameinfo(
                 self.ares_channel,
                 c_addr,
                 mem::size_of::<libc::sockaddr>() as c_ares_sys::ares_socklen_t,
                 flags.bits(),
-                Some(get_name_info_callback::<F>),
+                Some(tracked_name_info_callback),
                 c_arg);
         }
+        self.collect_panic();
+        handle
     }
 
     /// Cancel all requests made on this `Channel`.
     ///
     /// Callbacks will be invoked for each pending query, passing a result
-    /// `Err(AresError::ECANCELLED)`.
+    /// `Err(AresError::ECANCELLED)`.  To cancel a single query, use the
+    /// `QueryHandle` returned when it was made.
+    ///
+    /// If a handler or callback panicked, then the panic is resumed here.
     pub fn cancel(&mut self) {
         unsafe { c_ares_sys::ares_cancel(self.ares_channel); }
+        self.resume_panic();
+    }
+
+    // Keep hold of any panic from a callback during the last call into
+    // c-ares, to be re-raised by `process_fd()`.
+    fn collect_panic(&mut self) {
+        if let Some(payload) = unwind::take() {
+            if self.panic.is_none() {
+                self.panic = Some(payload);
+            }
+        }
+    }
+
+    // Re-raise any panic from a callback, since the last time we did so.
+    fn resume_panic(&mut self) {
+        self.collect_panic();
+        if let Some(payload) = self.panic.take() {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    // Returns how long until the earliest deadline of a query that's still
+    // pending, if there is one.
+    fn next_deadline(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.deadlines
+            .iter()
+            .filter(|&&(_, ref query)| {
+                query.upgrade().map_or(false, |query| query.is_pending())
+            })
+            .map(|&(deadline, _)| {
+                if deadline > now {
+                    deadline - now
+                } else {
+                    Duration::new(0, 0)
+                }
+            })
+            .min()
+    }
+
+    // Fail any queries whose deadlines have passed, and forget about those
+    // that have completed.
+    fn expire_deadlines(&mut self) {
+        if self.deadlines.is_empty() {
+            return
+        }
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        self.deadlines.retain(|&(deadline, ref query)| {
+            match query.upgrade() {
+                Some(ref query) if !query.is_pending() => false,
+                Some(query) => {
+                    if deadline <= now {
+                        expired.push(query);
+                        false
+                    } else {
+                        true
+                    }
+                },
+                None => false,
+            }
+        });
+        for query in expired {
+            query.finish(Some(AresError::ETIMEOUT));
+        }
+    }
+
+    // Make a query of class `dns_class` and type `query_type` for `name` - or,
+    // if `search` is set, search for it in the configured search domains.
+    // On completion, `callback` is called with `handler` and the response.
+    fn make_query<F>(
+        &mut self,
+        name: &str,
+        dns_class: u16,
+        query_type: u16,
+        search: bool,
+        callback: QueryCallback,
+        handler: F) -> QueryHandle {
+        let (handle, c_arg) = QueryHandle::query(handler, callback);
+        match to_c_name(name) {
+            Ok(c_name) => unsafe {
+                let make_query = if search {
+                    c_ares_sys::ares_search
+                } else {
+                    c_ares_sys::ares_query
+                };
+                make_query(
+                    self.ares_channel,
+                    c_name.as_ptr(),
+                    dns_class as libc::c_int,
+                    query_type as libc::c_int,
+                    Some(tracked_query_callback),
+                    c_arg);
+            },
+            Err(error) => fail_query(c_arg, error),
+        }
+        self.collect_panic();
+        handle
+    }
+
+    // Make a query for DNSSEC records.  If we've been asked to set the DNSSEC
+    // OK bit then c-ares can't build the query, so we do it ourselves.
+    fn query_dnssec<F>(
+        &mut self,
+        name: &str,
+        query_type: QueryType,
+        callback: QueryCallback,
+        handler: F) -> QueryHandle {
+        if !self.dnssec_ok {
+            return self.make_query(
+                name,
+                DnsClass::IN as u16,
+                query_type as u16,
+                false,
+                callback,
+                handler)
+        }
+
+        // Older versions of c-ares send the query with whatever ID it has, so
+        // give it one that an attacker can't guess.
+        let (handle, c_arg) = QueryHandle::query(handler, callback);
+        let query = MessageBuilder::new()
+            .set_id(random_query_id())
+            .set_recursion_desired(self.recursion_desired)
+            .add_question(name, query_type as u16, DnsClass::IN as u16)
+            .set_edns(self.edns_payload_size)
+            .set_dnssec_ok(true)
+            .build();
+        match query {
+            Ok(query) => unsafe {
+                c_ares_sys::ares_send(
+                    self.ares_channel,
+                    query.as_ptr(),
+                    query.len() as libc::c_int,
+                    Some(tracked_query_callback),
+                    c_arg);
+            },
+            Err(error) => fail_query(c_arg, error),
+        }
+        self.collect_panic();
+        handle
     }
 }
 
+// Report the failure of a query, made with `tracked_query_callback`, as
+// c-ares would.
+fn fail_query(c_arg: *mut libc::c_void, error: AresError) {
+    unsafe {
+        tracked_query_callback(
+            c_arg,
+            error as libc::c_int,
+            0,
+            ptr::null_mut(),
+            0);
+    }
+}
+
+// Returns an unpredictable query ID.  `RandomState` is seeded from the
+// operating system's random number generator, and each one has new keys.
+fn random_query_id() -> u16 {
+    RandomState::new().build_hasher().finish() as u16
+}
+
 impl Drop for Channel {
     fn drop(&mut self) {
         unsafe {
             c_ares_sys::ares_destroy(self.ares_channel);
             c_ares_sys::ares_library_cleanup();
         }
+
+        // Destroying the channel fails any outstanding queries.  Panicking
+        // in drop isn't safe, so any panic from their handlers is discarded.
+        unwind::take();
     }
 }
 
@@ -651,8 +1553,10 @@ pub unsafe extern "C" fn socket_state_callback<F>(
     readable: libc::c_int,
     writable: libc::c_int)
     where F: FnMut(io::RawFd, bool, bool) + 'static {
-    let handler = data as *mut F;
-    (*handler)(socket_fd as io::RawFd, readable != 0, writable != 0);
+    unwind::catch(|| {
+        let handler = data as *mut F;
+        (*handler)(socket_fd as io::RawFd, readable != 0, writable != 0);
+    });
 }
 
 /// Information about the set of sockets that `c-ares` is interested in, as
@@ -720,3 +1624,41 @@ impl<'a> IntoIterator for &'a GetSock {
         self.iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use error::AresError;
+    use super::{
+        Options,
+        random_query_id,
+    };
+
+    #[test]
+    fn query_ids_vary() {
+        let first = random_query_id();
+        assert!((0..16).any(|_| random_query_id() != first));
+    }
+
+    #[test]
+    fn options_reject_bad_strings() {
+        let mut options = Options::new();
+        assert!(options.set_domains(&["example.com", "example.org."]).is_ok());
+        assert_eq!(options.domains.len(), 2);
+        match options.set_domains(&["example.com", "exa\0mple.com"]) {
+            Err(AresError::EBADNAME) => (),
+            _ => panic!("unexpected result"),
+        }
+        match options.set_domains(&["bad..name"]) {
+            Err(AresError::EBADNAME) => (),
+            _ => panic!("unexpected result"),
+        }
+        assert_eq!(options.domains.len(), 2);
+
+        assert!(options.set_lookups("fb").is_ok());
+        match options.set_lookups("f\0b") {
+            Err(AresError::EBADSTR) => (),
+            _ => panic!("unexpected result"),
+        }
+        assert!(options.lookups.is_some());
+    }
+}
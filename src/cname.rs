@@ -1,15 +1,16 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::borrow::Cow;
 use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::mem;
 use std::ptr;
 use std::slice;
-use std::str;
 
 use error::AresError;
 use types::hostent;
+use unwind;
 use utils::ares_error;
 
 /// The result of a successful CNAME lookup.
@@ -18,6 +19,17 @@ pub struct CNameResult {
     phantom: PhantomData<hostent>,
 }
 
+/// An owned copy of a `CNameResult`, as returned by
+/// `CNameResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedCNameResult {
+    /// The canonical name.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub cname: String,
+
+    /// The canonical name, exactly as received.
+    pub cname_bytes: Vec<u8>,
+}
+
 impl CNameResult {
     /// Obtain a `CNameResult` from the response to a CNAME lookup.
     pub fn parse_from(data: &[u8]) -> Result<CNameResult, AresError> {
@@ -46,11 +58,26 @@ impl CNameResult {
         }
     }
 
-    /// Returns the canonical name record from this `CNameResult`.
-    pub fn cname(&self) -> &str {
+    /// Returns the canonical name record from this `CNameResult`.  Any invalid
+    /// UTF-8 is replaced by U+FFFD.
+    pub fn cname(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.cname_bytes())
+    }
+
+    /// Returns the canonical name record from this `CNameResult`, exactly as
+    /// received.
+    pub fn cname_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr((*self.hostent).h_name);
-            str::from_utf8_unchecked(c_str.to_bytes())
+            c_str.to_bytes()
+        }
+    }
+
+    /// Returns an owned copy of this `CNameResult`, which can outlive it.
+    pub fn to_owned(&self) -> OwnedCNameResult {
+        OwnedCNameResult {
+            cname: self.cname().into_owned(),
+            cname_bytes: self.cname_bytes().to_vec(),
         }
     }
 }
@@ -74,12 +101,14 @@ pub unsafe extern "C" fn query_cname_callback<F>(
     abuf: *mut libc::c_uchar,
     alen: libc::c_int)
     where F: FnOnce(Result<CNameResult, AresError>) + 'static {
-    let handler: Box<F> = mem::transmute(arg);
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let data = slice::from_raw_parts(abuf, alen as usize);
-        CNameResult::parse_from(data)
-    };
-    handler(result);
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            CNameResult::parse_from(data)
+        };
+        handler(result);
+    });
 }
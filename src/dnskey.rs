@@ -0,0 +1,274 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful DNSKEY lookup.
+pub struct DNSKEYResults {
+    dnskey_records: Vec<DNSKEYRecord>,
+}
+
+/// The contents of a single DNSKEY record.
+pub struct DNSKEYResult<'a> {
+    // A single result - reference into a `DNSKEYResults`.
+    dnskey_record: &'a DNSKEYRecord,
+}
+
+/// An owned copy of a single DNSKEY record, as returned by
+/// `DNSKEYResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedDNSKEYResult {
+    /// The flags.
+    pub flags: u16,
+
+    /// The protocol.
+    pub protocol: u8,
+
+    /// The algorithm.
+    pub algorithm: u8,
+
+    /// The public key.
+    pub public_key: Vec<u8>,
+}
+
+// A DNSKEY record, as parsed from the response.
+struct DNSKEYRecord {
+    flags: u16,
+    protocol: u8,
+    algorithm: u8,
+    public_key: Vec<u8>,
+}
+
+impl DNSKEYResults {
+    /// Obtain a `DNSKEYResults` from the response to a DNSKEY lookup.
+    pub fn parse_from(data: &[u8]) -> Result<DNSKEYResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut dnskey_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::DNSKEY)? {
+            let mut reader = Reader::new(rr.rdata());
+            let dnskey_record = DNSKEYRecord {
+                flags: reader.read_u16()?,
+                protocol: reader.read_u8()?,
+                algorithm: reader.read_u8()?,
+                public_key: reader.read_rest().to_vec(),
+            };
+            dnskey_records.push(dnskey_record);
+        }
+        let results = DNSKEYResults {
+            dnskey_records: dnskey_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `DNSKEYResult` values in this
+    /// `DNSKEYResults`.
+    pub fn iter(&self) -> DNSKEYResultsIterator {
+        DNSKEYResultsIterator {
+            next: self.dnskey_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `DNSKEYResult` values in this
+    /// `DNSKEYResults`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedDNSKEYResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct DNSKEYResultsIterator<'a> {
+    next: slice::Iter<'a, DNSKEYRecord>,
+}
+
+impl<'a> Iterator for DNSKEYResultsIterator<'a> {
+    type Item = DNSKEYResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|dnskey_record| {
+            DNSKEYResult {
+                dnskey_record: dnskey_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a DNSKEYResults {
+    type Item = DNSKEYResult<'a>;
+    type IntoIter = DNSKEYResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> DNSKEYResult<'a> {
+    /// Returns the flags from this `DNSKEYResult`.
+    pub fn flags(&self) -> u16 {
+        self.dnskey_record.flags
+    }
+
+    /// Returns the protocol from this `DNSKEYResult`.  This is always 3.
+    pub fn protocol(&self) -> u8 {
+        self.dnskey_record.protocol
+    }
+
+    /// Returns the algorithm of the key in this `DNSKEYResult`.
+    pub fn algorithm(&self) -> u8 {
+        self.dnskey_record.algorithm
+    }
+
+    /// Returns the public key from this `DNSKEYResult`.
+    pub fn public_key(&self) -> &[u8] {
+        &self.dnskey_record.public_key
+    }
+
+    /// Returns whether the key in this `DNSKEYResult` is a zone key.
+    pub fn is_zone_key(&self) -> bool {
+        self.flags() & 0x0100 != 0
+    }
+
+    /// Returns whether the key in this `DNSKEYResult` is marked as a secure
+    /// entry point - that is, as a key-signing key.
+    pub fn is_secure_entry_point(&self) -> bool {
+        self.flags() & 0x0001 != 0
+    }
+
+    /// Returns the key tag of the key in this `DNSKEYResult`, as used by DS
+    /// and RRSIG records to refer to it.
+    pub fn key_tag(&self) -> u16 {
+        // See RFC 4034, appendix B.
+        let rdata = [
+            (self.flags() >> 8) as u8,
+            self.flags() as u8,
+            self.protocol(),
+            self.algorithm(),
+        ];
+        let sum = rdata
+            .iter()
+            .chain(self.public_key().iter())
+            .enumerate()
+            .fold(0u32, |sum, (index, &byte)| {
+                if index % 2 == 0 {
+                    sum + ((byte as u32) << 8)
+                } else {
+                    sum + byte as u32
+                }
+            });
+        (sum + (sum >> 16)) as u16
+    }
+
+    /// Returns an owned copy of this `DNSKEYResult`.
+    pub fn to_owned(&self) -> OwnedDNSKEYResult {
+        OwnedDNSKEYResult {
+            flags: self.flags(),
+            protocol: self.protocol(),
+            algorithm: self.algorithm(),
+            public_key: self.public_key().to_vec(),
+        }
+    }
+}
+
+pub unsafe extern "C" fn query_dnskey_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<DNSKEYResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            DNSKEYResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_rejects_overlong_rdata,
+        assert_rejects_rdata,
+        parse_test_response,
+    };
+    use types::QueryType;
+    use super::{
+        OwnedDNSKEYResult,
+        DNSKEYResults,
+    };
+
+    // The example key from RFC 4034, section 2.3.
+    const RFC_4034_KEY: [u8; 134] = [
+            0x01, 0x00, 0x03, 0x05, 0x01, 0x03, 0xd2, 0x2a, 0x6c, 0xa7, 0x7f,
+            0x35, 0xb8, 0x93, 0x20, 0x6f, 0xd3, 0x5e, 0x4c, 0x50, 0x6d, 0x83,
+            0x78, 0x84, 0x37, 0x09, 0xb9, 0x7e, 0x04, 0x16, 0x47, 0xe1, 0xbf,
+            0xf4, 0x3d, 0x8d, 0x64, 0xc6, 0x49, 0xaf, 0x1e, 0x37, 0x19, 0x73,
+            0xc9, 0xe8, 0x91, 0xfc, 0xe3, 0xdf, 0x51, 0x9a, 0x8c, 0x84, 0x0a,
+            0x63, 0xee, 0x42, 0xa6, 0xd2, 0xeb, 0xdd, 0xbb, 0x97, 0x03, 0x5d,
+            0x21, 0x5a, 0xa4, 0xe4, 0x17, 0xb1, 0xfa, 0x45, 0xfa, 0x11, 0xa9,
+            0x74, 0x1e, 0xa2, 0x09, 0x8c, 0x1d, 0xfa, 0x5f, 0xb5, 0xfe, 0xb3,
+            0x32, 0xfd, 0x4b, 0xc8, 0x15, 0x20, 0x89, 0xae, 0xf3, 0x6b, 0xa6,
+            0x44, 0xcc, 0xe2, 0x41, 0x3b, 0x3b, 0x72, 0xbe, 0x18, 0xcb, 0xef,
+            0x8d, 0xa2, 0x53, 0xf4, 0xe9, 0x3d, 0x21, 0x03, 0x86, 0x6d, 0x92,
+            0x34, 0xa2, 0xe2, 0x8d, 0xf5, 0x29, 0xa6, 0x7d, 0x54, 0x68, 0xdb,
+            0xef, 0xe3,
+    ];
+
+    #[test]
+    fn parses_records() {
+        let results = parse_test_response(
+            DNSKEYResults::parse_from,
+            QueryType::DNSKEY,
+            &[&RFC_4034_KEY]).unwrap();
+        let result = results.iter().next().unwrap();
+        assert_eq!(result.flags(), 256);
+        assert_eq!(result.protocol(), 3);
+        assert_eq!(result.algorithm(), 5);
+        assert_eq!(result.public_key(), &RFC_4034_KEY[4..]);
+        assert!(result.is_zone_key());
+        assert!(!result.is_secure_entry_point());
+        assert_eq!(result.key_tag(), 2642);
+
+        let results = parse_test_response(
+            DNSKEYResults::parse_from,
+            QueryType::DNSKEY,
+            &[&[1, 1, 3, 8]]).unwrap();
+        let expected = vec![
+            OwnedDNSKEYResult {
+                flags: 257,
+                protocol: 3,
+                algorithm: 8,
+                public_key: Vec::new(),
+            },
+        ];
+        assert_eq!(results.to_owned(), expected);
+        assert!(results.iter().next().unwrap().is_secure_entry_point());
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(DNSKEYResults::parse_from, QueryType::DNSKEY, &[
+            &[],
+            &[1, 0, 3]]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        let rdata: &[u8] = &[1, 0, 3, 5, 0xff];
+        assert_rejects_overlong_rdata(
+            DNSKEYResults::parse_from,
+            QueryType::DNSKEY,
+            rdata);
+    }
+}
@@ -0,0 +1,213 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful DS lookup.
+pub struct DSResults {
+    ds_records: Vec<DSRecord>,
+}
+
+/// The contents of a single DS record.
+pub struct DSResult<'a> {
+    // A single result - reference into a `DSResults`.
+    ds_record: &'a DSRecord,
+}
+
+/// An owned copy of a single DS record, as returned by
+/// `DSResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedDSResult {
+    /// The key tag.
+    pub key_tag: u16,
+
+    /// The algorithm.
+    pub algorithm: u8,
+
+    /// The digest type.
+    pub digest_type: u8,
+
+    /// The digest.
+    pub digest: Vec<u8>,
+}
+
+// A DS record, as parsed from the response.
+struct DSRecord {
+    key_tag: u16,
+    algorithm: u8,
+    digest_type: u8,
+    digest: Vec<u8>,
+}
+
+impl DSResults {
+    /// Obtain a `DSResults` from the response to a DS lookup.
+    pub fn parse_from(data: &[u8]) -> Result<DSResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut ds_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::DS)? {
+            let mut reader = Reader::new(rr.rdata());
+            let ds_record = DSRecord {
+                key_tag: reader.read_u16()?,
+                algorithm: reader.read_u8()?,
+                digest_type: reader.read_u8()?,
+                digest: reader.read_rest().to_vec(),
+            };
+            ds_records.push(ds_record);
+        }
+        let results = DSResults {
+            ds_records: ds_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `DSResult` values in this
+    /// `DSResults`.
+    pub fn iter(&self) -> DSResultsIterator {
+        DSResultsIterator {
+            next: self.ds_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `DSResult` values in this
+    /// `DSResults`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedDSResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct DSResultsIterator<'a> {
+    next: slice::Iter<'a, DSRecord>,
+}
+
+impl<'a> Iterator for DSResultsIterator<'a> {
+    type Item = DSResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|ds_record| {
+            DSResult {
+                ds_record: ds_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a DSResults {
+    type Item = DSResult<'a>;
+    type IntoIter = DSResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> DSResult<'a> {
+    /// Returns the key tag of the DNSKEY that this `DSResult` refers to.
+    pub fn key_tag(&self) -> u16 {
+        self.ds_record.key_tag
+    }
+
+    /// Returns the algorithm of the DNSKEY that this `DSResult` refers to.
+    pub fn algorithm(&self) -> u8 {
+        self.ds_record.algorithm
+    }
+
+    /// Returns the type of digest in this `DSResult`.
+    pub fn digest_type(&self) -> u8 {
+        self.ds_record.digest_type
+    }
+
+    /// Returns the digest of the DNSKEY from this `DSResult`.
+    pub fn digest(&self) -> &[u8] {
+        &self.ds_record.digest
+    }
+
+    /// Returns an owned copy of this `DSResult`.
+    pub fn to_owned(&self) -> OwnedDSResult {
+        OwnedDSResult {
+            key_tag: self.key_tag(),
+            algorithm: self.algorithm(),
+            digest_type: self.digest_type(),
+            digest: self.digest().to_vec(),
+        }
+    }
+}
+
+pub unsafe extern "C" fn query_ds_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<DSResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            DSResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_rejects_overlong_rdata,
+        assert_rejects_rdata,
+        parse_test_response,
+    };
+    use types::QueryType;
+    use super::{
+        OwnedDSResult,
+        DSResults,
+    };
+
+    #[test]
+    fn parses_records() {
+        let results = parse_test_response(
+            DSResults::parse_from,
+            QueryType::DS,
+            &[&[0x0a, 0x52, 8, 2, 0x12, 0x34]]).unwrap();
+        let result = results.iter().next().unwrap();
+        assert_eq!(result.key_tag(), 2642);
+        assert_eq!(result.algorithm(), 8);
+        assert_eq!(result.digest_type(), 2);
+        assert_eq!(result.digest(), &[0x12, 0x34]);
+        let expected = vec![
+            OwnedDSResult {
+                key_tag: 2642,
+                algorithm: 8,
+                digest_type: 2,
+                digest: vec![0x12, 0x34],
+            },
+        ];
+        assert_eq!(results.to_owned(), expected);
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(DSResults::parse_from, QueryType::DS, &[
+            &[],
+            &[0x0a, 0x52, 8]]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        let rdata: &[u8] = &[0x0a, 0x52, 8, 2, 0x12];
+        assert_rejects_overlong_rdata(
+            DSResults::parse_from,
+            QueryType::DS,
+            rdata);
+    }
+}
@@ -0,0 +1,94 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::ffi::CStr;
+use std::ptr;
+use std::slice;
+
+use error::AresError;
+use utils::ares_error;
+
+/// Decode the possibly compressed domain name found at `offset` in the DNS
+/// message `buf`.
+///
+/// Returns the name, and the number of bytes that its encoding occupies at
+/// `offset` - so that the caller can skip over it.  Any invalid UTF-8 in the
+/// name is replaced by U+FFFD: use `expand_name_bytes()` to get it exactly.
+pub fn expand_name(
+    buf: &[u8],
+    offset: usize) -> Result<(String, usize), AresError> {
+    let (name, length) = expand_name_bytes(buf, offset)?;
+    let name = match String::from_utf8(name) {
+        Ok(name) => name,
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    };
+    Ok((name, length))
+}
+
+/// Decode the possibly compressed domain name found at `offset` in the DNS
+/// message `buf`.
+///
+/// As `expand_name()`, but returns the name exactly as `c-ares` expands it.
+pub fn expand_name_bytes(
+    buf: &[u8],
+    offset: usize) -> Result<(Vec<u8>, usize), AresError> {
+    if offset >= buf.len() {
+        return Err(AresError::EBADRESP)
+    }
+    let mut name: *mut libc::c_char = ptr::null_mut();
+    let mut length: libc::c_long = 0;
+    let status = unsafe {
+        c_ares_sys::ares_expand_name(
+            buf[offset..].as_ptr(),
+            buf.as_ptr(),
+            buf.len() as libc::c_int,
+            &mut name,
+            &mut length)
+    };
+    if status != c_ares_sys::ARES_SUCCESS {
+        return Err(ares_error(status))
+    }
+    let expanded = unsafe {
+        let c_str = CStr::from_ptr(name);
+        let expanded = c_str.to_bytes().to_vec();
+        c_ares_sys::ares_free_string(name as *mut libc::c_void);
+        expanded
+    };
+    Ok((expanded, length as usize))
+}
+
+/// Decode the length-prefixed character-string found at `offset` in the DNS
+/// message `buf`.
+///
+/// Returns the string, and the number of bytes that its encoding occupies at
+/// `offset`.  A character-string may hold arbitrary bytes, so is returned as
+/// such.
+pub fn expand_string(
+    buf: &[u8],
+    offset: usize) -> Result<(Vec<u8>, usize), AresError> {
+    if offset >= buf.len() {
+        return Err(AresError::EBADRESP)
+    }
+    let mut string: *mut libc::c_uchar = ptr::null_mut();
+    let mut length: libc::c_long = 0;
+    let status = unsafe {
+        c_ares_sys::ares_expand_string(
+            buf[offset..].as_ptr(),
+            buf.as_ptr(),
+            buf.len() as libc::c_int,
+            &mut string,
+            &mut length)
+    };
+    if status != c_ares_sys::ARES_SUCCESS {
+        return Err(ares_error(status))
+    }
+
+    // The encoding is a length byte followed by the string itself.
+    let expanded = unsafe {
+        let data = slice::from_raw_parts(string, length as usize - 1);
+        let expanded = data.to_vec();
+        c_ares_sys::ares_free_string(string as *mut libc::c_void);
+        expanded
+    };
+    Ok((expanded, length as usize))
+}
@@ -0,0 +1,871 @@
+extern crate libc;
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::os::unix::io;
+use std::panic::{
+    self,
+    AssertUnwindSafe,
+};
+use std::pin::Pin;
+use std::sync::{
+    Arc,
+    Mutex,
+    MutexGuard,
+};
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use std::task::{
+    Context,
+    Poll,
+    Waker,
+};
+use std::thread;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use a::AResults;
+use aaaa::AAAAResults;
+use caa::CAAResults;
+use channel::{
+    Channel,
+    Options,
+};
+use cname::CNameResult;
+use dnskey::DNSKEYResults;
+use ds::DSResults;
+use error::AresError;
+use handle::QueryHandle;
+use hinfo::HINFOResults;
+use host::OwnedHostResults;
+use loc::LOCResults;
+use mx::MXResults;
+use nameinfo::OwnedNameInfoResult;
+use naptr::NAPTRResults;
+use ni_flags::NIFlags;
+use ns::NSResults;
+use nsec::NSECResults;
+use nsec3::NSEC3Results;
+use poll::poll_fds;
+use ptr::PTRResults;
+use rp::RPResults;
+use rrsig::RRSIGResults;
+use srv::SRVResults;
+use sshfp::SSHFPResults;
+use svcb::SVCBResults;
+use tlsa::TLSAResults;
+use types::{
+    AddressFamily,
+    INVALID_FD,
+    IpAddr,
+};
+use uri::URIResults;
+use txt::TXTResults;
+use soa::SOAResult;
+
+/// A resolver whose queries return futures.
+///
+/// A `FutureResolver` owns a `Channel`, and runs an event loop on a
+/// background thread which listens on the sockets that `c-ares` asks for.
+/// Each query returns a `CAresFuture`, which completes when the query does;
+/// any number of queries can be in progress at once.
+///
+/// Dropping the `FutureResolver` stops the event loop.  Any queries still
+/// outstanding complete with `Err(AresError::EDESTRUCTION)`.
+pub struct FutureResolver {
+    channel: Arc<Mutex<Channel>>,
+    wakeup: Arc<Wakeup>,
+    quit: Arc<AtomicBool>,
+    event_loop: Option<thread::JoinHandle<()>>,
+    query_timeout: Mutex<Option<Duration>>,
+}
+
+impl FutureResolver {
+    /// Create a new `FutureResolver`, using the given `Options`.
+    pub fn new(options: Options) -> Result<FutureResolver, AresError> {
+        let channel = Arc::new(Mutex::new(Channel::new(options)?));
+        let wakeup = match Wakeup::new() {
+            Some(wakeup) => Arc::new(wakeup),
+            None => return Err(AresError::UNKNOWN),
+        };
+        let quit = Arc::new(AtomicBool::new(false));
+        let event_loop = EventLoop {
+            channel: channel.clone(),
+            wakeup: wakeup.clone(),
+            quit: quit.clone(),
+        };
+        let handle = thread::spawn(move || event_loop.run());
+        let resolver = FutureResolver {
+            channel: channel,
+            wakeup: wakeup,
+            quit: quit,
+            event_loop: Some(handle),
+            query_timeout: Mutex::new(None),
+        };
+        Ok(resolver)
+    }
+
+    /// Set the list of servers to contact, instead of the servers specified
+    /// in resolv.conf or the local named.
+    ///
+    /// String format is `host[:port]`.  IPv6 addresses with ports require
+    /// square brackets eg `[2001:4860:4860::8888]:53`.
+    pub fn set_servers(&self, servers: &[&str]) -> Result<&Self, AresError> {
+        self.channel.lock().unwrap().set_servers(servers).map(|_| self)
+    }
+
+    /// Give each query made from now on a deadline, `timeout` after it's
+    /// made.  Queries that take longer fail with `ETIMEOUT`.  See
+    /// `Channel::set_query_deadline()`.
+    pub fn set_query_timeout(&self, timeout: Duration) -> &Self {
+        *self.query_timeout.lock().unwrap() = Some(timeout);
+        self
+    }
+
+    /// Look up the A records associated with `name`.
+    pub fn query_a(
+        &self,
+        name: &str) -> CAresFuture<Result<AResults, AresError>> {
+        self.submit(|channel, done| channel.query_a(name, done))
+    }
+
+    /// Look up the AAAA records associated with `name`.
+    pub fn query_aaaa(
+        &self,
+        name: &str) -> CAresFuture<Result<AAAAResults, AresError>> {
+        self.submit(|channel, done| channel.query_aaaa(name, done))
+    }
+
+    /// Look up the CNAME record associated with `name`.
+    pub fn query_cname(
+        &self,
+        name: &str) -> CAresFuture<Result<CNameResult, AresError>> {
+        self.submit(|channel, done| channel.query_cname(name, done))
+    }
+
+    /// Look up the MX records associated with `name`.
+    pub fn query_mx(
+        &self,
+        name: &str) -> CAresFuture<Result<MXResults, AresError>> {
+        self.submit(|channel, done| channel.query_mx(name, done))
+    }
+
+    /// Look up the NAPTR records associated with `name`.
+    pub fn query_naptr(
+        &self,
+        name: &str) -> CAresFuture<Result<NAPTRResults, AresError>> {
+        self.submit(|channel, done| channel.query_naptr(name, done))
+    }
+
+    /// Look up the NS records associated with `name`.
+    pub fn query_ns(
+        &self,
+        name: &str) -> CAresFuture<Result<NSResults, AresError>> {
+        self.submit(|channel, done| channel.query_ns(name, done))
+    }
+
+    /// Look up the PTR records associated with `name`.
+    pub fn query_ptr(
+        &self,
+        name: &str) -> CAresFuture<Result<PTRResults, AresError>> {
+        self.submit(|channel, done| channel.query_ptr(name, done))
+    }
+
+    /// Look up the SRV records associated with `name`.
+    pub fn query_srv(
+        &self,
+        name: &str) -> CAresFuture<Result<SRVResults, AresError>> {
+        self.submit(|channel, done| channel.query_srv(name, done))
+    }
+
+    /// Look up the TXT records associated with `name`.
+    pub fn query_txt(
+        &self,
+        name: &str) -> CAresFuture<Result<TXTResults, AresError>> {
+        self.submit(|channel, done| channel.query_txt(name, done))
+    }
+
+    /// Look up the SOA record associated with `name`.
+    pub fn query_soa(
+        &self,
+        name: &str) -> CAresFuture<Result<SOAResult, AresError>> {
+        self.submit(|channel, done| channel.query_soa(name, done))
+    }
+
+    /// Look up the CAA records associated with `name`.
+    pub fn query_caa(
+        &self,
+        name: &str) -> CAresFuture<Result<CAAResults, AresError>> {
+        self.submit(|channel, done| channel.query_caa(name, done))
+    }
+
+    /// Look up the TLSA records associated with `name`.
+    pub fn query_tlsa(
+        &self,
+        name: &str) -> CAresFuture<Result<TLSAResults, AresError>> {
+        self.submit(|channel, done| channel.query_tlsa(name, done))
+    }
+
+    /// Look up the SSHFP records associated with `name`.
+    pub fn query_sshfp(
+        &self,
+        name: &str) -> CAresFuture<Result<SSHFPResults, AresError>> {
+        self.submit(|channel, done| channel.query_sshfp(name, done))
+    }
+
+    /// Look up the SVCB records associated with `name`.
+    pub fn query_svcb(
+        &self,
+        name: &str) -> CAresFuture<Result<SVCBResults, AresError>> {
+        self.submit(|channel, done| channel.query_svcb(name, done))
+    }
+
+    /// Look up the HTTPS records associated with `name`.
+    pub fn query_https(
+        &self,
+        name: &str) -> CAresFuture<Result<SVCBResults, AresError>> {
+        self.submit(|channel, done| channel.query_https(name, done))
+    }
+
+    /// Look up the DNSKEY records associated with `name`.
+    pub fn query_dnskey(
+        &self,
+        name: &str) -> CAresFuture<Result<DNSKEYResults, AresError>> {
+        self.submit(|channel, done| channel.query_dnskey(name, done))
+    }
+
+    /// Look up the DS records associated with `name`.
+    pub fn query_ds(
+        &self,
+        name: &str) -> CAresFuture<Result<DSResults, AresError>> {
+        self.submit(|channel, done| channel.query_ds(name, done))
+    }
+
+    /// Look up the RRSIG records associated with `name`.
+    pub fn query_rrsig(
+        &self,
+        name: &str) -> CAresFuture<Result<RRSIGResults, AresError>> {
+        self.submit(|channel, done| channel.query_rrsig(name, done))
+    }
+
+    /// Look up the NSEC records associated with `name`.
+    pub fn query_nsec(
+        &self,
+        name: &str) -> CAresFuture<Result<NSECResults, AresError>> {
+        self.submit(|channel, done| channel.query_nsec(name, done))
+    }
+
+    /// Look up the NSEC3 records associated with `name`.
+    pub fn query_nsec3(
+        &self,
+        name: &str) -> CAresFuture<Result<NSEC3Results, AresError>> {
+        self.submit(|channel, done| channel.query_nsec3(name, done))
+    }
+
+    /// Look up the URI records associated with `name`.
+    pub fn query_uri(
+        &self,
+        name: &str) -> CAresFuture<Result<URIResults, AresError>> {
+        self.submit(|channel, done| channel.query_uri(name, done))
+    }
+
+    /// Look up the HINFO records associated with `name`.
+    pub fn query_hinfo(
+        &self,
+        name: &str) -> CAresFuture<Result<HINFOResults, AresError>> {
+        self.submit(|channel, done| channel.query_hinfo(name, done))
+    }
+
+    /// Look up the LOC records associated with `name`.
+    pub fn query_loc(
+        &self,
+        name: &str) -> CAresFuture<Result<LOCResults, AresError>> {
+        self.submit(|channel, done| channel.query_loc(name, done))
+    }
+
+    /// Look up the RP records associated with `name`.
+    pub fn query_rp(
+        &self,
+        name: &str) -> CAresFuture<Result<RPResults, AresError>> {
+        self.submit(|channel, done| channel.query_rp(name, done))
+    }
+
+    /// Search for the A records associated with `name`.
+    ///
+    /// Like `query_a()`, but applies the configured search domains.
+    pub fn search_a(
+        &self,
+        name: &str) -> CAresFuture<Result<AResults, AresError>> {
+        self.submit(|channel, done| channel.search_a(name, done))
+    }
+
+    /// Search for the AAAA records associated with `name`.
+    ///
+    /// Like `query_aaaa()`, but applies the configured search domains.
+    pub fn search_aaaa(
+        &self,
+        name: &str) -> CAresFuture<Result<AAAAResults, AresError>> {
+        self.submit(|channel, done| channel.search_aaaa(name, done))
+    }
+
+    /// Search for the CNAME record associated with `name`.
+    ///
+    /// Like `query_cname()`, but applies the configured search domains.
+    pub fn search_cname(
+        &self,
+        name: &str) -> CAresFuture<Result<CNameResult, AresError>> {
+        self.submit(|channel, done| channel.search_cname(name, done))
+    }
+
+    /// Search for the MX records associated with `name`.
+    ///
+    /// Like `query_mx()`, but applies the configured search domains.
+    pub fn search_mx(
+        &self,
+        name: &str) -> CAresFuture<Result<MXResults, AresError>> {
+        self.submit(|channel, done| channel.search_mx(name, done))
+    }
+
+    /// Search for the NAPTR records associated with `name`.
+    ///
+    /// Like `query_naptr()`, but applies the configured search domains.
+    pub fn search_naptr(
+        &self,
+        name: &str) -> CAresFuture<Result<NAPTRResults, AresError>> {
+        self.submit(|channel, done| channel.search_naptr(name, done))
+    }
+
+    /// Search for the NS records associated with `name`.
+    ///
+    /// Like `query_ns()`, but applies the configured search domains.
+    pub fn search_ns(
+        &self,
+        name: &str) -> CAresFuture<Result<NSResults, AresError>> {
+        self.submit(|channel, done| channel.search_ns(name, done))
+    }
+
+    /// Search for the PTR records associated with `name`.
+    ///
+    /// Like `query_ptr()`, but applies the configured search domains.
+    pub fn search_ptr(
+        &self,
+        name: &str) -> CAresFuture<Result<PTRResults, AresError>> {
+        self.submit(|channel, done| channel.search_ptr(name, done))
+    }
+
+    /// Search for the SRV records associated with `name`.
+    ///
+    /// Like `query_srv()`, but applies the configured search domains.
+    pub fn search_srv(
+        &self,
+        name: &str) -> CAresFuture<Result<SRVResults, AresError>> {
+        self.submit(|channel, done| channel.search_srv(name, done))
+    }
+
+    /// Search for the TXT records associated with `name`.
+    ///
+    /// Like `query_txt()`, but applies the configured search domains.
+    pub fn search_txt(
+        &self,
+        name: &str) -> CAresFuture<Result<TXTResults, AresError>> {
+        self.submit(|channel, done| channel.search_txt(name, done))
+    }
+
+    /// Search for the SOA records associated with `name`.
+    ///
+    /// Like `query_soa()`, but applies the configured search domains.
+    pub fn search_soa(
+        &self,
+        name: &str) -> CAresFuture<Result<SOAResult, AresError>> {
+        self.submit(|channel, done| channel.search_soa(name, done))
+    }
+
+    /// Search for the CAA records associated with `name`.
+    ///
+    /// Like `query_caa()`, but applies the configured search domains.
+    pub fn search_caa(
+        &self,
+        name: &str) -> CAresFuture<Result<CAAResults, AresError>> {
+        self.submit(|channel, done| channel.search_caa(name, done))
+    }
+
+    /// Search for the TLSA records associated with `name`.
+    ///
+    /// Like `query_tlsa()`, but applies the configured search domains.
+    pub fn search_tlsa(
+        &self,
+        name: &str) -> CAresFuture<Result<TLSAResults, AresError>> {
+        self.submit(|channel, done| channel.search_tlsa(name, done))
+    }
+
+    /// Search for the SSHFP records associated with `name`.
+    ///
+    /// Like `query_sshfp()`, but applies the configured search domains.
+    pub fn search_sshfp(
+        &self,
+        name: &str) -> CAresFuture<Result<SSHFPResults, AresError>> {
+        self.submit(|channel, done| channel.search_sshfp(name, done))
+    }
+
+    /// Search for the SVCB records associated with `name`.
+    ///
+    /// Like `query_svcb()`, but applies the configured search domains.
+    pub fn search_svcb(
+        &self,
+        name: &str) -> CAresFuture<Result<SVCBResults, AresError>> {
+        self.submit(|channel, done| channel.search_svcb(name, done))
+    }
+
+    /// Search for the HTTPS records associated with `name`.
+    ///
+    /// Like `query_https()`, but applies the configured search domains.
+    pub fn search_https(
+        &self,
+        name: &str) -> CAresFuture<Result<SVCBResults, AresError>> {
+        self.submit(|channel, done| channel.search_https(name, done))
+    }
+
+    /// Search for the URI records associated with `name`.
+    ///
+    /// Like `query_uri()`, but applies the configured search domains.
+    pub fn search_uri(
+        &self,
+        name: &str) -> CAresFuture<Result<URIResults, AresError>> {
+        self.submit(|channel, done| channel.search_uri(name, done))
+    }
+
+    /// Search for the HINFO records associated with `name`.
+    ///
+    /// Like `query_hinfo()`, but applies the configured search domains.
+    pub fn search_hinfo(
+        &self,
+        name: &str) -> CAresFuture<Result<HINFOResults, AresError>> {
+        self.submit(|channel, done| channel.search_hinfo(name, done))
+    }
+
+    /// Search for the LOC records associated with `name`.
+    ///
+    /// Like `query_loc()`, but applies the configured search domains.
+    pub fn search_loc(
+        &self,
+        name: &str) -> CAresFuture<Result<LOCResults, AresError>> {
+        self.submit(|channel, done| channel.search_loc(name, done))
+    }
+
+    /// Search for the RP records associated with `name`.
+    ///
+    /// Like `query_rp()`, but applies the configured search domains.
+    pub fn search_rp(
+        &self,
+        name: &str) -> CAresFuture<Result<RPResults, AresError>> {
+        self.submit(|channel, done| channel.search_rp(name, done))
+    }
+
+    /// Look up the records of type `query_type` and class `dns_class`
+    /// associated with `name`.  The future completes with the raw DNS
+    /// response, as for `Channel::query_raw()`.
+    pub fn query_raw(
+        &self,
+        name: &str,
+        dns_class: u16,
+        query_type: u16) -> CAresFuture<Result<Vec<u8>, AresError>> {
+        self.submit(|channel, done| {
+            channel.query_raw(name, dns_class, query_type, move |result| {
+                done(result.map(|response| response.to_vec()))
+            })
+        })
+    }
+
+    /// Search for the records of type `query_type` and class `dns_class`
+    /// associated with `name`.
+    ///
+    /// Like `query_raw()`, but applies the configured search domains.
+    pub fn search_raw(
+        &self,
+        name: &str,
+        dns_class: u16,
+        query_type: u16) -> CAresFuture<Result<Vec<u8>, AresError>> {
+        self.submit(|channel, done| {
+            channel.search_raw(name, dns_class, query_type, move |result| {
+                done(result.map(|response| response.to_vec()))
+            })
+        })
+    }
+
+    /// Send a DNS query message, such as one built by `create_query()`.  The
+    /// future completes with the raw DNS response.
+    pub fn send_raw(
+        &self,
+        query: &[u8]) -> CAresFuture<Result<Vec<u8>, AresError>> {
+        self.submit(|channel, done| {
+            channel.send_raw(query, move |result| {
+                done(result.map(|response| response.to_vec()))
+            })
+        })
+    }
+
+    /// Perform a host query by address.
+    pub fn get_host_by_address(
+        &self,
+        address: &IpAddr)
+        -> CAresFuture<Result<OwnedHostResults, AresError>> {
+        self.submit(|channel, done| {
+            channel.get_host_by_address(address, move |result| {
+                done(result.map(|results| results.to_owned()))
+            })
+        })
+    }
+
+    /// Perform a host query by name.
+    pub fn get_host_by_name(
+        &self,
+        name: &str,
+        family: AddressFamily)
+        -> CAresFuture<Result<OwnedHostResults, AresError>> {
+        self.submit(|channel, done| {
+            channel.get_host_by_name(name, family, move |result| {
+                done(result.map(|results| results.to_owned()))
+            })
+        })
+    }
+
+    /// Address-to-nodename translation in protocol-independent manner.
+    pub fn get_name_info(
+        &self,
+        address: &SocketAddr,
+        flags: NIFlags)
+        -> CAresFuture<Result<OwnedNameInfoResult, AresError>> {
+        self.submit(|channel, done| {
+            channel.get_name_info(address, flags, move |result| {
+                done(result.map(|result| result.to_owned()))
+            })
+        })
+    }
+
+    // Make a query using `query`, which is passed the `Channel` and a
+    // callback to complete with the result.
+    fn submit<R, Q>(&self, query: Q) -> CAresFuture<Result<R, AresError>>
+        where R: Send + 'static,
+              Q: FnOnce(&mut Channel,
+                        Box<FnOnce(Result<R, AresError>) + Send>)
+                 -> QueryHandle {
+        let (future, done) = CAresFuture::new();
+        {
+            let mut channel = self.channel.lock().unwrap();
+            let handle = query(&mut channel, done);
+            if let Some(timeout) = *self.query_timeout.lock().unwrap() {
+                channel.set_query_deadline(&handle, Instant::now() + timeout);
+            }
+        }
+
+        // Prod the event loop, so that it listens on any new sockets, and
+        // notices any new deadline.
+        self.wakeup.notify();
+        future
+    }
+}
+
+impl Drop for FutureResolver {
+    fn drop(&mut self) {
+        self.quit.store(true, Ordering::SeqCst);
+        self.wakeup.notify();
+        if let Some(handle) = self.event_loop.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// A future that completes when a query made by a `FutureResolver` does.
+///
+/// Dropping the future discards the query's result, when it arrives.
+pub struct CAresFuture<T> {
+    shared: Arc<Mutex<SharedState<T>>>,
+    target: Arc<Target<T>>,
+}
+
+struct SharedState<T> {
+    result: Option<T>,
+    waker: Option<Waker>,
+}
+
+// Where a `Completer` delivers its result: emptied if the future is dropped
+// first.
+type Target<T> = Mutex<Option<Arc<Mutex<SharedState<T>>>>>;
+
+impl<T> CAresFuture<Result<T, AresError>> where T: Send + 'static {
+    // Returns a new future, and the callback that completes it.  If the
+    // callback is dropped without being called - say, because the handler
+    // that owned it panicked - then the future completes with
+    // `Err(AresError::ECANCELLED)`, rather than never completing at all.
+    pub(crate) fn new()
+        -> (CAresFuture<Result<T, AresError>>,
+            Box<FnOnce(Result<T, AresError>) + Send>) {
+        let shared = Arc::new(Mutex::new(SharedState {
+            result: None,
+            waker: None,
+        }));
+        let target = Arc::new(Mutex::new(Some(shared.clone())));
+        let mut completer = Completer {
+            target: target.clone(),
+        };
+        let done = Box::new(move |result| completer.complete(result));
+        let future = CAresFuture {
+            shared: shared,
+            target: target,
+        };
+        (future, done)
+    }
+}
+
+impl<T> Drop for CAresFuture<T> {
+    fn drop(&mut self) {
+        lock(&self.target).take();
+    }
+}
+
+// Locks `mutex`, even if a panic poisoned it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+// Completes a `CAresFuture`, either with the result or - if dropped without
+// being used - with `ECANCELLED`.
+struct Completer<T> {
+    target: Arc<Target<Result<T, AresError>>>,
+}
+
+impl<T> Completer<T> {
+    fn complete(&mut self, result: Result<T, AresError>) {
+        let shared = match lock(&self.target).take() {
+            Some(shared) => shared,
+            None => return,
+        };
+        let waker = {
+            let mut state = lock(&shared);
+            state.result = Some(result);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        self.complete(Err(AresError::ECANCELLED));
+    }
+}
+
+impl<T> Future for CAresFuture<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<T> {
+        let mut state = self.shared.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            },
+        }
+    }
+}
+
+// The event loop that runs on a background thread, listening on the sockets
+// that c-ares is interested in.
+struct EventLoop {
+    channel: Arc<Mutex<Channel>>,
+    wakeup: Arc<Wakeup>,
+    quit: Arc<AtomicBool>,
+}
+
+impl EventLoop {
+    fn run(self) {
+        while !self.quit.load(Ordering::SeqCst) {
+            // Find out what c-ares is interested in.  We listen on the
+            // wakeup pipe too, so that we hear about new queries.
+            let (mut fds, timeout) = {
+                let channel = self.channel.lock().unwrap();
+                let fds: Vec<_> = channel.get_sock().iter().collect();
+                (fds, channel.timeout(None))
+            };
+            let wakeup_fd = self.wakeup.read_fd;
+            fds.push((wakeup_fd, true, false));
+
+            // Wait for something to happen, and tell the channel about it.
+            //
+            // A panicking handler is re-raised by `process_fd()`.  There's
+            // no-one to hand that on to here, and letting it unwind would
+            // poison the channel and stop the event loop, leaving every other
+            // query hanging.  So catch it, and carry on: the future belonging
+            // to that handler has already been completed with `ECANCELLED`.
+            let events = poll_fds(&fds, timeout);
+            let mut channel = self.channel.lock().unwrap();
+            let mut processed = false;
+            for (fd, readable, writable) in events {
+                if fd == wakeup_fd {
+                    self.wakeup.drain();
+                } else {
+                    let read_fd = if readable { fd } else { INVALID_FD };
+                    let write_fd = if writable { fd } else { INVALID_FD };
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+                        channel.process_fd(read_fd, write_fd)
+                    }));
+                    processed = true;
+                }
+            }
+            if !processed {
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+                    channel.process_timeouts()
+                }));
+            }
+        }
+    }
+}
+
+// A pipe, used to wake up the event loop.
+struct Wakeup {
+    read_fd: io::RawFd,
+    write_fd: io::RawFd,
+}
+
+impl Wakeup {
+    // Both ends of the pipe are non-blocking, so that neither a full pipe
+    // nor a spurious wakeup can stall anyone; and close-on-exec, so that they
+    // don't leak into child processes.
+    fn new() -> Option<Wakeup> {
+        let mut fds = [0; 2];
+        let rc = unsafe { libc::pipe(fds.as_mut_ptr()) };
+        if rc != 0 {
+            return None
+        }
+        let wakeup = Wakeup {
+            read_fd: fds[0],
+            write_fd: fds[1],
+        };
+        if configure_fd(wakeup.read_fd) && configure_fd(wakeup.write_fd) {
+            Some(wakeup)
+        } else {
+            None
+        }
+    }
+
+    fn notify(&self) {
+        let byte = [0u8];
+        unsafe {
+            libc::write(
+                self.write_fd,
+                byte.as_ptr() as *const libc::c_void,
+                1);
+        }
+    }
+
+    fn drain(&self) {
+        let mut buf = [0u8; 64];
+        loop {
+            let count = unsafe {
+                libc::read(
+                    self.read_fd,
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len() as libc::size_t)
+            };
+            if count < buf.len() as libc::ssize_t {
+                break
+            }
+        }
+    }
+}
+
+impl Drop for Wakeup {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.read_fd);
+            libc::close(self.write_fd);
+        }
+    }
+}
+
+// Makes a file descriptor non-blocking and close-on-exec.  Returns false on
+// failure.
+fn configure_fd(fd: io::RawFd) -> bool {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 ||
+            libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            return false
+        }
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        flags >= 0 &&
+            libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) >= 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate libc;
+
+    use std::sync::Arc;
+
+    use error::AresError;
+    use super::{
+        CAresFuture,
+        Wakeup,
+    };
+
+    #[test]
+    fn future_completes_with_result() {
+        let (future, done) = CAresFuture::<Result<u32, AresError>>::new();
+        done(Ok(42));
+        let result = future.shared.lock().unwrap().result.take();
+        match result {
+            Some(Ok(42)) => (),
+            _ => panic!("unexpected result"),
+        }
+    }
+
+    #[test]
+    fn future_cancelled_when_callback_dropped() {
+        let (future, done) = CAresFuture::<Result<u32, AresError>>::new();
+        drop(done);
+        let result = future.shared.lock().unwrap().result.take();
+        match result {
+            Some(Err(AresError::ECANCELLED)) => (),
+            _ => panic!("unexpected result"),
+        }
+    }
+
+    #[test]
+    fn dropping_future_discards_result() {
+        let (future, done) = CAresFuture::<Result<u32, AresError>>::new();
+        let shared = future.shared.clone();
+        assert_eq!(Arc::strong_count(&shared), 3);
+        drop(future);
+        assert_eq!(Arc::strong_count(&shared), 1);
+        done(Ok(42));
+        assert!(shared.lock().unwrap().result.is_none());
+    }
+
+    #[test]
+    fn wakeup_is_nonblocking_and_cloexec() {
+        let wakeup = Wakeup::new().unwrap();
+        for &fd in &[wakeup.read_fd, wakeup.write_fd] {
+            let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+            assert!(flags & libc::O_NONBLOCK != 0);
+            let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+            assert!(flags & libc::FD_CLOEXEC != 0);
+        }
+
+        // Draining an empty pipe must not block.
+        wakeup.drain();
+        wakeup.notify();
+        wakeup.drain();
+    }
+}
@@ -0,0 +1,218 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::marker::PhantomData;
+use std::mem;
+use std::panic;
+use std::ptr;
+use std::sync::{
+    Arc,
+    Mutex,
+    Weak,
+};
+
+use error::AresError;
+use unwind;
+
+// The shapes of callback that c-ares makes when a request completes.
+pub type QueryCallback = unsafe extern "C" fn(
+    *mut libc::c_void,
+    libc::c_int,
+    libc::c_int,
+    *mut libc::c_uchar,
+    libc::c_int);
+pub type HostCallback = unsafe extern "C" fn(
+    *mut libc::c_void,
+    libc::c_int,
+    libc::c_int,
+    *mut c_ares_sys::Struct_hostent);
+pub type NameInfoCallback = unsafe extern "C" fn(
+    *mut libc::c_void,
+    libc::c_int,
+    libc::c_int,
+    *mut libc::c_char,
+    *mut libc::c_char);
+
+// A callback that can be made to report failure, without any results.
+pub trait Callback: Copy {
+    unsafe fn fail(self, arg: *mut libc::c_void, error: AresError);
+}
+
+impl Callback for QueryCallback {
+    unsafe fn fail(self, arg: *mut libc::c_void, error: AresError) {
+        self(arg, error as libc::c_int, 0, ptr::null_mut(), 0);
+    }
+}
+
+impl Callback for HostCallback {
+    unsafe fn fail(self, arg: *mut libc::c_void, error: AresError) {
+        self(arg, error as libc::c_int, 0, ptr::null_mut());
+    }
+}
+
+impl Callback for NameInfoCallback {
+    unsafe fn fail(self, arg: *mut libc::c_void, error: AresError) {
+        self(arg, error as libc::c_int, 0, ptr::null_mut(), ptr::null_mut());
+    }
+}
+
+// A request that hasn't yet been completed: the callback to make when it is,
+// and the argument to make it with.  `free` releases the argument without
+// making the callback.
+struct Pending<C> {
+    callback: C,
+    arg: *mut libc::c_void,
+    free: unsafe fn(*mut libc::c_void),
+}
+
+// Shared between c-ares and a `QueryHandle`: whichever gets there first
+// takes the pending request and completes it.
+type Shared<C> = Mutex<Option<Pending<C>>>;
+
+// A request that can be completed early: with `error`, or silently if that
+// is `None`.
+pub trait Finish {
+    fn finish(&self, error: Option<AresError>);
+    fn is_pending(&self) -> bool;
+}
+
+impl<C> Finish for Shared<C> where C: Callback {
+    fn is_pending(&self) -> bool {
+        self.lock().unwrap().is_some()
+    }
+
+    fn finish(&self, error: Option<AresError>) {
+        let pending = self.lock().unwrap().take();
+        if let Some(pending) = pending {
+            unsafe {
+                match error {
+                    Some(error) => pending.callback.fail(pending.arg, error),
+                    None => (pending.free)(pending.arg),
+                }
+            }
+        }
+    }
+}
+
+/// A handle on a query, as returned by the query methods on a `Channel`.
+///
+/// The handle can be used to cancel the query without disturbing any others
+/// on the same channel, or to give it a deadline of its own with
+/// `Channel::set_query_deadline()`.  Dropping the handle does not cancel the
+/// query.
+pub struct QueryHandle {
+    shared: Arc<Finish>,
+
+    // The handler may be called from `cancel()`, so the handle must stay on
+    // the thread that made the query.
+    phantom: PhantomData<*const ()>,
+}
+
+impl QueryHandle {
+    // Prepares to make a query.  `callback` and `handler` are what c-ares
+    // would otherwise have been given: instead it should be given the
+    // returned argument, with `tracked_query_callback`.  This passes
+    // completion on to them - unless the query has been cancelled already.
+    pub(crate) fn query<F>(
+        handler: F,
+        callback: QueryCallback) -> (QueryHandle, *mut libc::c_void) {
+        QueryHandle::new(handler, callback)
+    }
+
+    // As `query()`, but for `tracked_host_callback`.
+    pub(crate) fn host<F>(
+        handler: F,
+        callback: HostCallback) -> (QueryHandle, *mut libc::c_void) {
+        QueryHandle::new(handler, callback)
+    }
+
+    // As `query()`, but for `tracked_name_info_callback`.
+    pub(crate) fn name_info<F>(
+        handler: F,
+        callback: NameInfoCallback) -> (QueryHandle, *mut libc::c_void) {
+        QueryHandle::new(handler, callback)
+    }
+
+    fn new<F, C>(
+        handler: F,
+        callback: C) -> (QueryHandle, *mut libc::c_void)
+        where C: Callback + 'static {
+        let pending = Pending {
+            callback: callback,
+            arg: unsafe { mem::transmute(Box::new(handler)) },
+            free: free_handler::<F>,
+        };
+        let shared = Arc::new(Mutex::new(Some(pending)));
+        let c_arg = Box::into_raw(Box::new(shared.clone()));
+        let handle = QueryHandle {
+            shared: shared,
+            phantom: PhantomData,
+        };
+        (handle, c_arg as *mut libc::c_void)
+    }
+
+    /// Cancel the query.  Unless the query has already completed, its handler
+    /// is called straight away with `ECANCELLED`.
+    pub fn cancel(&self) {
+        self.shared.finish(Some(AresError::ECANCELLED));
+        if let Some(payload) = unwind::take() {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    /// Cancel the query, without calling its handler.
+    pub fn cancel_silently(&self) {
+        self.shared.finish(None);
+    }
+
+    // Returns a reference to the query, through which the `Channel` can
+    // complete it early.
+    pub(crate) fn downgrade(&self) -> Weak<Finish> {
+        Arc::downgrade(&self.shared)
+    }
+}
+
+unsafe fn free_handler<F>(arg: *mut libc::c_void) {
+    let handler: Box<F> = mem::transmute(arg);
+    drop(handler);
+}
+
+// Takes the pending request shared with a `QueryHandle`, if it's still
+// there.
+unsafe fn take_pending<C>(arg: *mut libc::c_void) -> Option<Pending<C>> {
+    let shared: Box<Arc<Shared<C>>> = Box::from_raw(arg as *mut _);
+    let pending = shared.lock().unwrap().take();
+    pending
+}
+
+pub unsafe extern "C" fn tracked_query_callback(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int) {
+    if let Some(pending) = take_pending::<QueryCallback>(arg) {
+        (pending.callback)(pending.arg, status, timeouts, abuf, alen);
+    }
+}
+
+pub unsafe extern "C" fn tracked_host_callback(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    timeouts: libc::c_int,
+    hostent: *mut c_ares_sys::Struct_hostent) {
+    if let Some(pending) = take_pending::<HostCallback>(arg) {
+        (pending.callback)(pending.arg, status, timeouts, hostent);
+    }
+}
+
+pub unsafe extern "C" fn tracked_name_info_callback(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    timeouts: libc::c_int,
+    node: *mut libc::c_char,
+    service: *mut libc::c_char) {
+    if let Some(pending) = take_pending::<NameInfoCallback>(arg) {
+        (pending.callback)(pending.arg, status, timeouts, node, service);
+    }
+}
@@ -0,0 +1,191 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful HINFO lookup.
+pub struct HINFOResults {
+    hinfo_records: Vec<HINFORecord>,
+}
+
+/// The contents of a single HINFO record.
+pub struct HINFOResult<'a> {
+    // A single result - reference into an `HINFOResults`.
+    hinfo_record: &'a HINFORecord,
+}
+
+/// An owned copy of a single HINFO record, as returned by
+/// `HINFOResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedHINFOResult {
+    /// The CPU type.
+    pub cpu: Vec<u8>,
+
+    /// The operating system.
+    pub os: Vec<u8>,
+}
+
+// An HINFO record, as parsed from the response.
+struct HINFORecord {
+    cpu: Vec<u8>,
+    os: Vec<u8>,
+}
+
+impl HINFOResults {
+    /// Obtain an `HINFOResults` from the response to an HINFO lookup.
+    pub fn parse_from(data: &[u8]) -> Result<HINFOResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut hinfo_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::HINFO)? {
+            let mut reader = Reader::new(rr.rdata());
+            let hinfo_record = HINFORecord {
+                cpu: reader.read_string()?.to_vec(),
+                os: reader.read_string()?.to_vec(),
+            };
+            if !reader.is_empty() {
+                return Err(AresError::EBADRESP)
+            }
+            hinfo_records.push(hinfo_record);
+        }
+        let results = HINFOResults {
+            hinfo_records: hinfo_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `HINFOResult` values in this
+    /// `HINFOResults`.
+    pub fn iter(&self) -> HINFOResultsIterator {
+        HINFOResultsIterator {
+            next: self.hinfo_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `HINFOResult` values in this
+    /// `HINFOResults`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedHINFOResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct HINFOResultsIterator<'a> {
+    next: slice::Iter<'a, HINFORecord>,
+}
+
+impl<'a> Iterator for HINFOResultsIterator<'a> {
+    type Item = HINFOResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|hinfo_record| {
+            HINFOResult {
+                hinfo_record: hinfo_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a HINFOResults {
+    type Item = HINFOResult<'a>;
+    type IntoIter = HINFOResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> HINFOResult<'a> {
+    /// Returns the CPU type from this `HINFOResult`.  This is a DNS
+    /// character-string, which need not be valid UTF-8.
+    pub fn cpu(&self) -> &[u8] {
+        &self.hinfo_record.cpu
+    }
+
+    /// Returns the operating system from this `HINFOResult`.  This is a DNS
+    /// character-string, which need not be valid UTF-8.
+    pub fn os(&self) -> &[u8] {
+        &self.hinfo_record.os
+    }
+
+    /// Returns an owned copy of this `HINFOResult`.
+    pub fn to_owned(&self) -> OwnedHINFOResult {
+        OwnedHINFOResult {
+            cpu: self.cpu().to_vec(),
+            os: self.os().to_vec(),
+        }
+    }
+}
+
+pub unsafe extern "C" fn query_hinfo_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<HINFOResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            HINFOResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_rejects_rdata,
+        parse_test_response,
+    };
+    use types::QueryType;
+    use super::{
+        HINFOResults,
+        OwnedHINFOResult,
+    };
+
+    #[test]
+    fn parses_records() {
+        let results = parse_test_response(
+            HINFOResults::parse_from,
+            QueryType::HINFO,
+            &[b"\x07RFC8482\x00"]).unwrap();
+        let result = results.iter().next().unwrap();
+        assert_eq!(result.cpu(), b"RFC8482");
+        assert_eq!(result.os(), b"");
+        let expected = vec![
+            OwnedHINFOResult {
+                cpu: b"RFC8482".to_vec(),
+                os: Vec::new(),
+            },
+        ];
+        assert_eq!(results.to_owned(), expected);
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(HINFOResults::parse_from, QueryType::HINFO, &[
+            b"",
+            b"\x03x86",
+            b"\x03x86\x05Linu"]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        assert_rejects_rdata(
+            HINFOResults::parse_from,
+            QueryType::HINFO,
+            &[b"\x03x86\x05Linux\x00"]);
+    }
+}
@@ -1,6 +1,7 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::borrow::Cow;
 use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::mem;
@@ -9,7 +10,6 @@ use std::net::{
     Ipv6Addr,
 };
 use std::ptr;
-use std::str;
 
 use error::AresError;
 use types::{
@@ -17,6 +17,7 @@ use types::{
     hostent,
     IpAddr,
 };
+use unwind;
 use utils::{
     address_family,
     ares_error,
@@ -40,6 +41,27 @@ pub struct HostAddressResult<'a> {
     phantom: PhantomData<&'a hostent>,
 }
 
+/// An owned copy of a `HostResults`, as returned by
+/// `HostResults::to_owned()`.  Unlike a `HostResults`, this can outlive the
+/// callback to which the results were passed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedHostResults {
+    /// The hostname.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub hostname: String,
+
+    /// The hostname, exactly as received.
+    pub hostname_bytes: Vec<u8>,
+
+    /// The aliases.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub aliases: Vec<String>,
+
+    /// The aliases, exactly as received.
+    pub alias_bytes: Vec<Vec<u8>>,
+
+    /// The addresses.
+    pub addresses: Vec<IpAddr>,
+}
+
 impl<'a> HostResults<'a> {
     fn new(hostent: &'a hostent) -> HostResults {
         HostResults {
@@ -47,11 +69,17 @@ impl<'a> HostResults<'a> {
         }
     }
 
-    /// Returns the hostname from this `HostResults`.
-    pub fn hostname(&self) -> &str {
+    /// Returns the hostname from this `HostResults`.  Any invalid UTF-8 is
+    /// replaced by U+FFFD.
+    pub fn hostname(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.hostname_bytes())
+    }
+
+    /// Returns the hostname from this `HostResults`, exactly as received.
+    pub fn hostname_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr((*self.hostent).h_name);
-            str::from_utf8_unchecked(c_str.to_bytes())
+            c_str.to_bytes()
         }
     }
 
@@ -80,6 +108,23 @@ impl<'a> HostResults<'a> {
             phantom: PhantomData,
         }
     }
+
+    /// Returns an owned copy of this `HostResults`.
+    pub fn to_owned(&self) -> OwnedHostResults {
+        OwnedHostResults {
+            hostname: self.hostname().into_owned(),
+            hostname_bytes: self.hostname_bytes().to_vec(),
+            aliases: self.aliases()
+                .map(|alias| alias.alias().into_owned())
+                .collect(),
+            alias_bytes: self.aliases()
+                .map(|alias| alias.alias_bytes().to_vec())
+                .collect(),
+            addresses: self.addresses()
+                .map(|address| address.ip_address())
+                .collect(),
+        }
+    }
 }
 
 pub struct HostAddressResultsIterator<'a> {
@@ -182,11 +227,17 @@ impl<'a> HostAddressResult<'a> {
 }
 
 impl<'a> HostAliasResult<'a> {
-    /// Returns the alias in this `HostAliasResult`.
-    pub fn alias(&self) -> &str {
+    /// Returns the alias in this `HostAliasResult`.  Any invalid UTF-8 is
+    /// replaced by U+FFFD.
+    pub fn alias(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.alias_bytes())
+    }
+
+    /// Returns the alias in this `HostAliasResult`, exactly as received.
+    pub fn alias_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr(self.h_alias);
-            str::from_utf8_unchecked(c_str.to_bytes())
+            c_str.to_bytes()
         }
     }
 }
@@ -197,13 +248,15 @@ pub unsafe extern "C" fn get_host_callback<F>(
     _timeouts: libc::c_int,
     hostent: *mut c_ares_sys::Struct_hostent)
     where F: FnOnce(Result<HostResults, AresError>) + 'static {
-    let handler: Box<F> = mem::transmute(arg);
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let hostent_ref = &*(hostent as *mut hostent);
-        let host_results = HostResults::new(hostent_ref);
-        Ok(host_results)
-    };
-    handler(result);
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let hostent_ref = &*(hostent as *mut hostent);
+            let host_results = HostResults::new(hostent_ref);
+            Ok(host_results)
+        };
+        handler(result);
+    });
 }
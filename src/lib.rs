@@ -5,7 +5,9 @@
 //! -  Create a `Channel`.
 //!
 //! -  Make queries on the `Channel`.  Queries all take callbacks, which will
-//!    be called when the query completes.
+//!    be called when the query completes.  The `query_*()` methods look up
+//!    exactly the name given; the `search_*()` methods apply the configured
+//!    search domains, as resolv.conf does.
 //!
 //! -  Have `c-ares` tell you what file descriptors to listen on for read and /
 //!    or write events.  You can do this either by providing a callback, which
@@ -19,80 +21,223 @@
 //!    on the `Channel` to tell `c-ares` what has happened.
 //!
 //! -  If you have queries pending and don't see events happening, you still
-//!    need to call `process_fd()` at some point anyway - to give `c-ares` an
-//!    opportunity to process any requests that have timed out.
+//!    need to give `c-ares` an opportunity to process any requests that have
+//!    timed out.  Use `timeout()` to find out how long you can wait for
+//!    events, and call `process_timeouts()` if that time passes without any.
+//!
+//! If you just want to make a few queries, and don't want to write an event
+//! loop, use a `BlockingResolver`.  This owns a `Channel` and does all of the
+//! above for you - but blocks until each query completes.
+//!
+//! Alternatively, a `FutureResolver` runs an event loop on a background
+//! thread, and returns a future from each query.  With the `tokio` feature
+//! enabled, a `TokioResolver` does the same but is driven by the Tokio
+//! runtime.
+//!
+//! To look at the whole of a response - header, authority and additional
+//! sections, EDNS options - use `query_raw()` and parse the result with
+//! `message::Message`.
+//!
+//! If you're already using `mio`, enable the `mio` feature and use a
+//! `mio::MioEventDriver` to drive the `Channel` from your own event loop.
 //!
 //! Complete examples showing how to use the library can be found
 //! [here](https://github.com/dimbleby/rust-c-ares/tree/master/examples).
 #[macro_use] extern crate bitflags;
+#[cfg(feature = "mio")] extern crate mio as mio_crate;
+#[cfg(feature = "tokio")] extern crate tokio;
 mod a;
 mod aaaa;
+mod blocking;
+mod caa;
 mod srv;
 mod channel;
 mod cname;
+mod dnskey;
+mod ds;
 mod error;
+mod expand;
 pub mod flags;
+mod futureresolver;
+mod handle;
+mod hinfo;
 mod host;
+mod loc;
+pub mod message;
+#[cfg(feature = "mio")] pub mod mio;
 mod mx;
 mod nameinfo;
 mod naptr;
 pub mod ni_flags;
 mod ns;
+mod nsec;
+mod nsec3;
+mod poll;
 mod ptr;
+mod query;
+mod rp;
+mod rrsig;
+mod sshfp;
+mod svcb;
+mod tlsa;
 mod types;
 mod txt;
+mod unwind;
+mod uri;
 mod soa;
+#[cfg(feature = "tokio")] mod tokioresolver;
 mod utils;
 
 // Re-export public interfaces.
 pub use a::{
     AResult,
     AResults,
+    OwnedAResult,
+    OwnedAResults,
 };
 pub use aaaa::{
     AAAAResult,
     AAAAResults,
+    OwnedAAAAResult,
+    OwnedAAAAResults,
 };
 pub use srv::{
+    OwnedSRVResult,
     SRVResult,
     SRVResults,
 };
+pub use blocking::BlockingResolver;
+pub use caa::{
+    CAAResult,
+    CAAResults,
+    OwnedCAAResult,
+};
 pub use channel::{
     Channel,
     GetSock,
     Options,
 };
-pub use cname::CNameResult;
+pub use cname::{
+    CNameResult,
+    OwnedCNameResult,
+};
+pub use dnskey::{
+    OwnedDNSKEYResult,
+    DNSKEYResult,
+    DNSKEYResults,
+};
+pub use ds::{
+    OwnedDSResult,
+    DSResult,
+    DSResults,
+};
 pub use error::AresError;
+pub use expand::{
+    expand_name,
+    expand_name_bytes,
+    expand_string,
+};
+pub use futureresolver::{
+    CAresFuture,
+    FutureResolver,
+};
+pub use handle::QueryHandle;
+pub use hinfo::{
+    OwnedHINFOResult,
+    HINFOResult,
+    HINFOResults,
+};
 pub use host::{
     HostAddressResult,
     HostAliasResult,
     HostResults,
+    OwnedHostResults,
+};
+pub use loc::{
+    OwnedLOCResult,
+    LOCResult,
+    LOCResults,
 };
 pub use mx::{
     MXResult,
     MXResults,
+    OwnedMXResult,
+};
+pub use nameinfo::{
+    NameInfoResult,
+    OwnedNameInfoResult,
 };
-pub use nameinfo::NameInfoResult;
 pub use naptr::{
     NAPTRResult,
     NAPTRResults,
+    OwnedNAPTRResult,
 };
 pub use ns::{
     NSResult,
     NSResults,
+    OwnedNSResult,
+};
+pub use nsec::{
+    OwnedNSECResult,
+    NSECResult,
+    NSECResults,
+};
+pub use nsec3::{
+    OwnedNSEC3Result,
+    NSEC3Result,
+    NSEC3Results,
 };
 pub use ptr::{
+    OwnedPTRResult,
     PTRResult,
     PTRResults,
 };
+pub use query::create_query;
+pub use rp::{
+    OwnedRPResult,
+    RPResult,
+    RPResults,
+};
+pub use rrsig::{
+    OwnedRRSIGResult,
+    RRSIGResult,
+    RRSIGResults,
+};
+pub use sshfp::{
+    OwnedSSHFPResult,
+    SSHFPResult,
+    SSHFPResults,
+};
+pub use svcb::{
+    OwnedSVCBResult,
+    SVCBResult,
+    SVCBResults,
+    SvcParam,
+};
+pub use tlsa::{
+    OwnedTLSAResult,
+    TLSAResult,
+    TLSAResults,
+};
 pub use types::{
     AddressFamily,
+    DnsClass,
     INVALID_FD,
     IpAddr,
+    QueryType,
 };
 pub use txt::{
+    OwnedTXTResult,
     TXTResult,
     TXTResults,
 };
-pub use soa::SOAResult;
+pub use uri::{
+    OwnedURIResult,
+    URIResult,
+    URIResults,
+};
+pub use soa::{
+    OwnedSOAResult,
+    SOAResult,
+};
+#[cfg(feature = "tokio")] pub use tokioresolver::TokioResolver;
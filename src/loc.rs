@@ -0,0 +1,308 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+// The encoded value of a latitude or longitude of zero.
+const EQUATOR: f64 = 2147483648.0;
+
+// The encoded value of an altitude of zero, in centimetres.
+const ALTITUDE_BASE: f64 = 10_000_000.0;
+
+/// The result of a successful LOC lookup.
+pub struct LOCResults {
+    loc_records: Vec<LOCRecord>,
+}
+
+/// The contents of a single LOC record.
+pub struct LOCResult<'a> {
+    // A single result - reference into a `LOCResults`.
+    loc_record: &'a LOCRecord,
+}
+
+/// An owned copy of a single LOC record, as returned by
+/// `LOCResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedLOCResult {
+    /// The diameter of a sphere enclosing the entity, in metres.
+    pub size: f64,
+
+    /// The horizontal precision, in metres.
+    pub horizontal_precision: f64,
+
+    /// The vertical precision, in metres.
+    pub vertical_precision: f64,
+
+    /// The latitude, in degrees north.
+    pub latitude: f64,
+
+    /// The longitude, in degrees east.
+    pub longitude: f64,
+
+    /// The altitude, in metres.
+    pub altitude: f64,
+}
+
+// A LOC record, as parsed from the response.  The values are kept in
+// their encoded form, and decoded on request.
+struct LOCRecord {
+    size: u8,
+    horizontal_precision: u8,
+    vertical_precision: u8,
+    latitude: u32,
+    longitude: u32,
+    altitude: u32,
+}
+
+impl LOCResults {
+    /// Obtain a `LOCResults` from the response to a LOC lookup.
+    pub fn parse_from(data: &[u8]) -> Result<LOCResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut loc_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::LOC)? {
+            let mut reader = Reader::new(rr.rdata());
+            let version = reader.read_u8()?;
+            if version != 0 {
+                return Err(AresError::EBADRESP)
+            }
+            let loc_record = LOCRecord {
+                size: reader.read_u8()?,
+                horizontal_precision: reader.read_u8()?,
+                vertical_precision: reader.read_u8()?,
+                latitude: reader.read_u32()?,
+                longitude: reader.read_u32()?,
+                altitude: reader.read_u32()?,
+            };
+            let precisions = [
+                loc_record.size,
+                loc_record.horizontal_precision,
+                loc_record.vertical_precision,
+            ];
+            if !reader.is_empty() ||
+                !precisions.iter().all(|&p| is_valid_precision(p)) {
+                return Err(AresError::EBADRESP)
+            }
+            loc_records.push(loc_record);
+        }
+        let results = LOCResults {
+            loc_records: loc_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `LOCResult` values in this
+    /// `LOCResults`.
+    pub fn iter(&self) -> LOCResultsIterator {
+        LOCResultsIterator {
+            next: self.loc_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `LOCResult` values in this
+    /// `LOCResults`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedLOCResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct LOCResultsIterator<'a> {
+    next: slice::Iter<'a, LOCRecord>,
+}
+
+impl<'a> Iterator for LOCResultsIterator<'a> {
+    type Item = LOCResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|loc_record| {
+            LOCResult {
+                loc_record: loc_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a LOCResults {
+    type Item = LOCResult<'a>;
+    type IntoIter = LOCResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> LOCResult<'a> {
+    /// Returns the diameter of a sphere enclosing the entity described by
+    /// this `LOCResult`, in metres.
+    pub fn size(&self) -> f64 {
+        decode_precision(self.loc_record.size)
+    }
+
+    /// Returns the horizontal precision of this `LOCResult`, in metres.
+    pub fn horizontal_precision(&self) -> f64 {
+        decode_precision(self.loc_record.horizontal_precision)
+    }
+
+    /// Returns the vertical precision of this `LOCResult`, in metres.
+    pub fn vertical_precision(&self) -> f64 {
+        decode_precision(self.loc_record.vertical_precision)
+    }
+
+    /// Returns the latitude from this `LOCResult`, in degrees.  Positive
+    /// values are north of the equator.
+    pub fn latitude(&self) -> f64 {
+        decode_angle(self.loc_record.latitude)
+    }
+
+    /// Returns the longitude from this `LOCResult`, in degrees.  Positive
+    /// values are east of the prime meridian.
+    pub fn longitude(&self) -> f64 {
+        decode_angle(self.loc_record.longitude)
+    }
+
+    /// Returns the altitude from this `LOCResult`, in metres above the WGS 84
+    /// reference spheroid.
+    pub fn altitude(&self) -> f64 {
+        (self.loc_record.altitude as f64 - ALTITUDE_BASE) / 100.0
+    }
+
+    /// Returns an owned copy of this `LOCResult`.
+    pub fn to_owned(&self) -> OwnedLOCResult {
+        OwnedLOCResult {
+            size: self.size(),
+            horizontal_precision: self.horizontal_precision(),
+            vertical_precision: self.vertical_precision(),
+            latitude: self.latitude(),
+            longitude: self.longitude(),
+            altitude: self.altitude(),
+        }
+    }
+}
+
+// Angles are encoded as thousandths of a second of arc, offset by 2^31.
+fn decode_angle(encoded: u32) -> f64 {
+    (encoded as f64 - EQUATOR) / 3_600_000.0
+}
+
+// Sizes and precisions are encoded in centimetres, as a base-ten mantissa
+// and exponent in the high and low nibbles.
+fn decode_precision(encoded: u8) -> f64 {
+    let mantissa = (encoded >> 4) as f64;
+    let exponent = (encoded & 0xf) as i32;
+    mantissa * 10f64.powi(exponent) / 100.0
+}
+
+// Both digits of an encoded size or precision must be in the range 0 - 9.
+fn is_valid_precision(encoded: u8) -> bool {
+    encoded >> 4 <= 9 && encoded & 0xf <= 9
+}
+
+pub unsafe extern "C" fn query_loc_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<LOCResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            LOCResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_bad_response,
+        assert_rejects_rdata,
+        parse_test_response,
+    };
+    use types::QueryType;
+    use super::LOCResults;
+
+    // A LOC record at 42.5N 71W, 12.34m up: 1m across, to within 10km
+    // horizontally and 10m vertically.
+    fn example() -> Vec<u8> {
+        let mut rdata = vec![0, 0x12, 0x16, 0x13];
+        for &value in &[2_300_483_648u32, 1_891_883_648, 10_001_234] {
+            rdata.extend_from_slice(&[
+                (value >> 24) as u8,
+                (value >> 16) as u8,
+                (value >> 8) as u8,
+                value as u8]);
+        }
+        rdata
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn parses_records() {
+        let results = parse_test_response(
+            LOCResults::parse_from,
+            QueryType::LOC,
+            &[&example()]).unwrap();
+        let result = results.iter().next().unwrap();
+        assert_close(result.size(), 1.0);
+        assert_close(result.horizontal_precision(), 10_000.0);
+        assert_close(result.vertical_precision(), 10.0);
+        assert_close(result.latitude(), 42.5);
+        assert_close(result.longitude(), -71.0);
+        assert_close(result.altitude(), 12.34);
+    }
+
+    #[test]
+    fn checks_version() {
+        let mut rdata = example();
+        rdata[0] = 1;
+        assert_rejects_rdata(LOCResults::parse_from, QueryType::LOC, &[&rdata]);
+    }
+
+    #[test]
+    fn rejects_bad_precisions() {
+        for index in 1..4 {
+            for &precision in &[0xa0, 0x0a] {
+                let mut rdata = example();
+                rdata[index] = precision;
+                assert_bad_response(parse_test_response(
+                    LOCResults::parse_from,
+                    QueryType::LOC,
+                    &[&rdata]));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        let rdata = example();
+        for length in 0..rdata.len() {
+            assert_bad_response(parse_test_response(
+                LOCResults::parse_from,
+                QueryType::LOC,
+                &[&rdata[..length]]));
+        }
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        let mut rdata = example();
+        rdata.push(0);
+        assert_rejects_rdata(LOCResults::parse_from, QueryType::LOC, &[&rdata]);
+    }
+}
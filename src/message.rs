@@ -0,0 +1,1371 @@
+//! Parsing of DNS messages.
+//!
+//! The `*Results` types each understand just one kind of record, and don't
+//! give access to the rest of the response.  A `Message` is the whole of a
+//! response - for instance as passed to the handler of `query_raw()` - parsed
+//! into its header and sections.  Going the other way, a `MessageBuilder`
+//! constructs messages - for instance to pass to `send_raw()`.
+//!
+//! Domain names are returned in presentation format, without a trailing dot:
+//! the root name is the empty string.  Dots and backslashes within a label
+//! are escaped with a backslash, and other non-printable characters are
+//! written as `\DDD`.
+use std::collections::HashMap;
+
+use error::AresError;
+use types::QueryType;
+
+// The largest number of compression pointers that we'll follow in a single
+// name.  A legitimate name can't need more than this.
+const MAX_POINTERS: usize = 128;
+
+// The longest that a name can be in its uncompressed wire format.
+const MAX_NAME_LENGTH: usize = 255;
+
+// The longest that a single label can be.
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// A parsed DNS message.
+#[derive(Debug, Clone)]
+pub struct Message {
+    data: Vec<u8>,
+    header: Header,
+    questions: Vec<Question>,
+    answers: Vec<ResourceRecord>,
+    authority: Vec<ResourceRecord>,
+    additional: Vec<ResourceRecord>,
+    opt: Option<Opt>,
+}
+
+/// The header of a DNS message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    id: u16,
+    flags: u16,
+}
+
+/// An entry in the question section of a DNS message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    name: String,
+    query_type: u16,
+    dns_class: u16,
+}
+
+/// A resource record, from the answer, authority or additional section of a
+/// DNS message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceRecord {
+    name: String,
+    rr_type: u16,
+    dns_class: u16,
+    ttl: u32,
+    rdata: Vec<u8>,
+    rdata_offset: usize,
+}
+
+/// The EDNS information from the OPT pseudo-record of a DNS message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opt {
+    udp_payload_size: u16,
+    extended_rcode: u8,
+    version: u8,
+    flags: u16,
+    options: Vec<EdnsOption>,
+}
+
+/// A single option from an OPT pseudo-record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdnsOption {
+    code: u16,
+    data: Vec<u8>,
+}
+
+impl Message {
+    /// Parse a `Message` from the raw bytes of a DNS message.
+    ///
+    /// Fails with `EBADRESP` if the message is malformed.
+    pub fn parse(data: &[u8]) -> Result<Message, AresError> {
+        let mut reader = Reader::new(data);
+        let id = reader.read_u16()?;
+        let flags = reader.read_u16()?;
+        let qdcount = reader.read_u16()?;
+        let ancount = reader.read_u16()?;
+        let nscount = reader.read_u16()?;
+        let arcount = reader.read_u16()?;
+
+        let mut questions = Vec::new();
+        for _ in 0..qdcount {
+            let question = Question {
+                name: reader.read_name()?,
+                query_type: reader.read_u16()?,
+                dns_class: reader.read_u16()?,
+            };
+            questions.push(question);
+        }
+        let answers = read_records(&mut reader, ancount)?;
+        let authority = read_records(&mut reader, nscount)?;
+        let mut additional = read_records(&mut reader, arcount)?;
+
+        let mut opt = None;
+        if let Some(index) = additional
+            .iter()
+            .position(|rr| rr.rr_type == QueryType::OPT as u16) {
+            let rr = additional.remove(index);
+            opt = Some(Opt::from_record(&rr)?);
+        }
+
+        let message = Message {
+            data: data.to_vec(),
+            header: Header {
+                id: id,
+                flags: flags,
+            },
+            questions: questions,
+            answers: answers,
+            authority: authority,
+            additional: additional,
+            opt: opt,
+        };
+        Ok(message)
+    }
+
+    /// Returns the header of this `Message`.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Returns the response code of this `Message`.
+    ///
+    /// If the message has an OPT record, this includes the extended bits.
+    pub fn rcode(&self) -> u16 {
+        let extended = self.opt.as_ref().map_or(0, |opt| opt.extended_rcode);
+        ((extended as u16) << 4) | self.header.rcode() as u16
+    }
+
+    /// Returns the question section of this `Message`.
+    pub fn questions(&self) -> &[Question] {
+        &self.questions
+    }
+
+    /// Returns the answer section of this `Message`.
+    pub fn answers(&self) -> &[ResourceRecord] {
+        &self.answers
+    }
+
+    /// Returns the authority section of this `Message`.
+    pub fn authority(&self) -> &[ResourceRecord] {
+        &self.authority
+    }
+
+    /// Returns the additional section of this `Message`.  This does not
+    /// include the OPT pseudo-record, if any: use `opt()` for that.
+    pub fn additional(&self) -> &[ResourceRecord] {
+        &self.additional
+    }
+
+    /// Returns the EDNS information from this `Message`, if it has an OPT
+    /// pseudo-record.
+    pub fn opt(&self) -> Option<&Opt> {
+        self.opt.as_ref()
+    }
+
+    /// Returns the raw bytes of this `Message`.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    // Returns a reader positioned at the rdata of `rr`, which must be a
+    // record from this message.  Use this rather than reading the rdata
+    // directly when it may contain compressed names.
+    pub(crate) fn rdata_reader<'a>(
+        &'a self,
+        rr: &ResourceRecord) -> Reader<'a> {
+        let end = rr.rdata_offset + rr.rdata.len();
+        Reader {
+            data: &self.data[..end],
+            pos: rr.rdata_offset,
+        }
+    }
+
+    // Returns the records in the answer section that have type `rr_type`.
+    // Fails with `ENODATA` if there are none, as c-ares's own parsers do.
+    pub(crate) fn answers_of_type(
+        &self,
+        rr_type: QueryType) -> Result<Vec<&ResourceRecord>, AresError> {
+        let answers: Vec<_> = self.answers
+            .iter()
+            .filter(|rr| rr.rr_type == rr_type as u16)
+            .collect();
+        if answers.is_empty() {
+            Err(AresError::ENODATA)
+        } else {
+            Ok(answers)
+        }
+    }
+}
+
+impl Header {
+    /// Returns the message ID.
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// Returns whether this message is a response (QR).
+    pub fn is_response(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+
+    /// Returns the opcode.
+    pub fn opcode(&self) -> u8 {
+        ((self.flags >> 11) & 0xf) as u8
+    }
+
+    /// Returns whether the answer is authoritative (AA).
+    pub fn authoritative(&self) -> bool {
+        self.flags & 0x0400 != 0
+    }
+
+    /// Returns whether the message was truncated (TC).
+    pub fn truncated(&self) -> bool {
+        self.flags & 0x0200 != 0
+    }
+
+    /// Returns whether recursion was desired (RD).
+    pub fn recursion_desired(&self) -> bool {
+        self.flags & 0x0100 != 0
+    }
+
+    /// Returns whether recursion is available (RA).
+    pub fn recursion_available(&self) -> bool {
+        self.flags & 0x0080 != 0
+    }
+
+    /// Returns whether the data has been authenticated (AD).
+    pub fn authentic_data(&self) -> bool {
+        self.flags & 0x0020 != 0
+    }
+
+    /// Returns whether checking is disabled (CD).
+    pub fn checking_disabled(&self) -> bool {
+        self.flags & 0x0010 != 0
+    }
+
+    /// Returns the four-bit response code from the header.  See also
+    /// `Message::rcode()`.
+    pub fn rcode(&self) -> u8 {
+        (self.flags & 0xf) as u8
+    }
+}
+
+impl Question {
+    /// Returns the name that was asked about.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type that was asked about.
+    pub fn query_type(&self) -> u16 {
+        self.query_type
+    }
+
+    /// Returns the class that was asked about.
+    pub fn dns_class(&self) -> u16 {
+        self.dns_class
+    }
+}
+
+impl ResourceRecord {
+    /// Returns the owner name of this record.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type of this record.
+    pub fn rr_type(&self) -> u16 {
+        self.rr_type
+    }
+
+    /// Returns the class of this record.
+    pub fn dns_class(&self) -> u16 {
+        self.dns_class
+    }
+
+    /// Returns the time-to-live of this record, in seconds.
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+
+    /// Returns the raw data of this record.  Any compressed names in the data
+    /// refer to the message as a whole.
+    pub fn rdata(&self) -> &[u8] {
+        &self.rdata
+    }
+}
+
+impl Opt {
+    fn from_record(rr: &ResourceRecord) -> Result<Opt, AresError> {
+        let mut options = Vec::new();
+        let mut reader = Reader::new(&rr.rdata);
+        while !reader.is_empty() {
+            let code = reader.read_u16()?;
+            let length = reader.read_u16()?;
+            let option = EdnsOption {
+                code: code,
+                data: reader.read_bytes(length as usize)?.to_vec(),
+            };
+            options.push(option);
+        }
+        let opt = Opt {
+            udp_payload_size: rr.dns_class,
+            extended_rcode: (rr.ttl >> 24) as u8,
+            version: (rr.ttl >> 16) as u8,
+            flags: rr.ttl as u16,
+            options: options,
+        };
+        Ok(opt)
+    }
+
+    /// Returns the UDP payload size advertised by the sender.
+    pub fn udp_payload_size(&self) -> u16 {
+        self.udp_payload_size
+    }
+
+    /// Returns the upper eight bits of the extended response code.
+    pub fn extended_rcode(&self) -> u8 {
+        self.extended_rcode
+    }
+
+    /// Returns the EDNS version.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns whether the DNSSEC OK bit is set.
+    pub fn dnssec_ok(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+
+    /// Returns the EDNS flags.
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    /// Returns the options carried in the OPT record.
+    pub fn options(&self) -> &[EdnsOption] {
+        &self.options
+    }
+}
+
+impl EdnsOption {
+    /// Returns the option code.
+    pub fn code(&self) -> u16 {
+        self.code
+    }
+
+    /// Returns the option data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+fn read_records(
+    reader: &mut Reader,
+    count: u16) -> Result<Vec<ResourceRecord>, AresError> {
+    let mut records = Vec::new();
+    for _ in 0..count {
+        let name = reader.read_name()?;
+        let rr_type = reader.read_u16()?;
+        let dns_class = reader.read_u16()?;
+        let ttl = reader.read_u32()?;
+        let length = reader.read_u16()?;
+        let rdata_offset = reader.pos;
+        let rdata = reader.read_bytes(length as usize)?.to_vec();
+        let rr = ResourceRecord {
+            name: name,
+            rr_type: rr_type,
+            dns_class: dns_class,
+            ttl: ttl,
+            rdata: rdata,
+            rdata_offset: rdata_offset,
+        };
+        records.push(rr);
+    }
+    Ok(records)
+}
+
+/// Builds DNS messages in wire format, for instance to pass to `send_raw()`.
+///
+/// Names are given in presentation format, as described in the module
+/// documentation, and are compressed where possible.  Record data is written
+/// exactly as given.
+#[derive(Debug, Clone)]
+pub struct MessageBuilder {
+    id: u16,
+    flags: u16,
+    rcode: u16,
+    questions: Vec<(String, u16, u16)>,
+    answers: Vec<RecordSpec>,
+    authority: Vec<RecordSpec>,
+    additional: Vec<RecordSpec>,
+    udp_payload_size: Option<u16>,
+    dnssec_ok: bool,
+    edns_options: Vec<EdnsOption>,
+}
+
+#[derive(Debug, Clone)]
+struct RecordSpec {
+    name: String,
+    rr_type: u16,
+    dns_class: u16,
+    ttl: u32,
+    rdata: Vec<u8>,
+}
+
+impl MessageBuilder {
+    /// Returns a fresh `MessageBuilder`, for a message with ID zero and no
+    /// flags, questions or records.
+    pub fn new() -> MessageBuilder {
+        MessageBuilder {
+            id: 0,
+            flags: 0,
+            rcode: 0,
+            questions: Vec::new(),
+            answers: Vec::new(),
+            authority: Vec::new(),
+            additional: Vec::new(),
+            udp_payload_size: None,
+            dnssec_ok: false,
+            edns_options: Vec::new(),
+        }
+    }
+
+    /// Set the message ID.
+    pub fn set_id(&mut self, id: u16) -> &mut Self {
+        self.id = id;
+        self
+    }
+
+    /// Set whether the message is a response (QR).
+    pub fn set_response(&mut self, response: bool) -> &mut Self {
+        self.set_flag(0x8000, response)
+    }
+
+    /// Set the opcode.  Only the low four bits are used.
+    pub fn set_opcode(&mut self, opcode: u8) -> &mut Self {
+        self.flags = (self.flags & !0x7800) | ((opcode as u16 & 0xf) << 11);
+        self
+    }
+
+    /// Set whether the answer is authoritative (AA).
+    pub fn set_authoritative(&mut self, authoritative: bool) -> &mut Self {
+        self.set_flag(0x0400, authoritative)
+    }
+
+    /// Set whether the message is truncated (TC).
+    pub fn set_truncated(&mut self, truncated: bool) -> &mut Self {
+        self.set_flag(0x0200, truncated)
+    }
+
+    /// Set whether recursion is desired (RD).
+    pub fn set_recursion_desired(&mut self, desired: bool) -> &mut Self {
+        self.set_flag(0x0100, desired)
+    }
+
+    /// Set whether recursion is available (RA).
+    pub fn set_recursion_available(&mut self, available: bool) -> &mut Self {
+        self.set_flag(0x0080, available)
+    }
+
+    /// Set whether the data has been authenticated (AD).
+    pub fn set_authentic_data(&mut self, authentic: bool) -> &mut Self {
+        self.set_flag(0x0020, authentic)
+    }
+
+    /// Set whether checking is disabled (CD).
+    pub fn set_checking_disabled(&mut self, disabled: bool) -> &mut Self {
+        self.set_flag(0x0010, disabled)
+    }
+
+    /// Set the response code.  Values greater than 15 need the extended bits
+    /// in the OPT record, so can only be used if EDNS is enabled.
+    pub fn set_rcode(&mut self, rcode: u16) -> &mut Self {
+        self.rcode = rcode;
+        self
+    }
+
+    /// Add an entry to the question section.
+    pub fn add_question(
+        &mut self,
+        name: &str,
+        query_type: u16,
+        dns_class: u16) -> &mut Self {
+        self.questions.push((name.to_owned(), query_type, dns_class));
+        self
+    }
+
+    /// Add a record to the answer section.
+    pub fn add_answer(
+        &mut self,
+        name: &str,
+        rr_type: u16,
+        dns_class: u16,
+        ttl: u32,
+        rdata: &[u8]) -> &mut Self {
+        let record = RecordSpec::new(name, rr_type, dns_class, ttl, rdata);
+        self.answers.push(record);
+        self
+    }
+
+    /// Add a record to the authority section.
+    pub fn add_authority(
+        &mut self,
+        name: &str,
+        rr_type: u16,
+        dns_class: u16,
+        ttl: u32,
+        rdata: &[u8]) -> &mut Self {
+        let record = RecordSpec::new(name, rr_type, dns_class, ttl, rdata);
+        self.authority.push(record);
+        self
+    }
+
+    /// Add a record to the additional section.  Use `set_edns()` rather than
+    /// adding an OPT record here.
+    pub fn add_additional(
+        &mut self,
+        name: &str,
+        rr_type: u16,
+        dns_class: u16,
+        ttl: u32,
+        rdata: &[u8]) -> &mut Self {
+        let record = RecordSpec::new(name, rr_type, dns_class, ttl, rdata);
+        self.additional.push(record);
+        self
+    }
+
+    /// Enable EDNS, advertising the given UDP payload size.  An OPT record
+    /// is added to the additional section.
+    pub fn set_edns(&mut self, udp_payload_size: u16) -> &mut Self {
+        self.udp_payload_size = Some(udp_payload_size);
+        self
+    }
+
+    /// Set the DNSSEC OK bit in the OPT record.  Building the message fails
+    /// if this is set but EDNS has not been enabled.
+    pub fn set_dnssec_ok(&mut self, dnssec_ok: bool) -> &mut Self {
+        self.dnssec_ok = dnssec_ok;
+        self
+    }
+
+    /// Add an option to the OPT record.  Building the message fails if EDNS
+    /// has not been enabled.
+    pub fn add_edns_option(&mut self, code: u16, data: &[u8]) -> &mut Self {
+        let option = EdnsOption {
+            code: code,
+            data: data.to_vec(),
+        };
+        self.edns_options.push(option);
+        self
+    }
+
+    /// Build the message.
+    ///
+    /// Fails with `EBADNAME` if any name is invalid, or with `EBADQUERY` if
+    /// the message can't otherwise be represented - for instance if a
+    /// section has too many entries, or if EDNS is needed but has not been
+    /// enabled.
+    pub fn build(&self) -> Result<Vec<u8>, AresError> {
+        let needs_edns = self.rcode > 0xf ||
+            self.dnssec_ok ||
+            !self.edns_options.is_empty();
+        if self.rcode > 0xfff ||
+            (needs_edns && self.udp_payload_size.is_none()) {
+            return Err(AresError::EBADQUERY)
+        }
+        let arcount = self.additional.len() +
+            self.udp_payload_size.map_or(0, |_| 1);
+
+        let mut writer = Writer::new();
+        writer.write_u16(self.id);
+        writer.write_u16(self.flags | (self.rcode & 0xf));
+        writer.write_count(self.questions.len())?;
+        writer.write_count(self.answers.len())?;
+        writer.write_count(self.authority.len())?;
+        writer.write_count(arcount)?;
+        for &(ref name, query_type, dns_class) in &self.questions {
+            writer.write_name(name)?;
+            writer.write_u16(query_type);
+            writer.write_u16(dns_class);
+        }
+        for record in self.answers
+            .iter()
+            .chain(self.authority.iter())
+            .chain(self.additional.iter()) {
+            writer.write_name(&record.name)?;
+            writer.write_u16(record.rr_type);
+            writer.write_u16(record.dns_class);
+            writer.write_u32(record.ttl);
+            writer.write_count(record.rdata.len())?;
+            writer.write_bytes(&record.rdata);
+        }
+        if let Some(udp_payload_size) = self.udp_payload_size {
+            let mut rdata = Vec::new();
+            for option in &self.edns_options {
+                if option.data.len() > 0xffff {
+                    return Err(AresError::EBADQUERY)
+                }
+                rdata.push((option.code >> 8) as u8);
+                rdata.push(option.code as u8);
+                rdata.push((option.data.len() >> 8) as u8);
+                rdata.push(option.data.len() as u8);
+                rdata.extend_from_slice(&option.data);
+            }
+            let extended_rcode = (self.rcode >> 4) as u32;
+            let flags = if self.dnssec_ok { 0x8000 } else { 0 };
+            writer.write_name("")?;
+            writer.write_u16(QueryType::OPT as u16);
+            writer.write_u16(udp_payload_size);
+            writer.write_u32((extended_rcode << 24) | flags);
+            writer.write_count(rdata.len())?;
+            writer.write_bytes(&rdata);
+        }
+        Ok(writer.data)
+    }
+
+    fn set_flag(&mut self, flag: u16, value: bool) -> &mut Self {
+        if value {
+            self.flags = self.flags | flag;
+        } else {
+            self.flags = self.flags & !flag;
+        }
+        self
+    }
+}
+
+impl RecordSpec {
+    fn new(
+        name: &str,
+        rr_type: u16,
+        dns_class: u16,
+        ttl: u32,
+        rdata: &[u8]) -> RecordSpec {
+        RecordSpec {
+            name: name.to_owned(),
+            rr_type: rr_type,
+            dns_class: dns_class,
+            ttl: ttl,
+            rdata: rdata.to_vec(),
+        }
+    }
+}
+
+// Writes values in DNS wire format, compressing names.
+struct Writer {
+    data: Vec<u8>,
+    names: HashMap<Vec<u8>, usize>,
+}
+
+impl Writer {
+    fn new() -> Writer {
+        Writer {
+            data: Vec::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    fn write_u16(&mut self, value: u16) {
+        self.data.push((value >> 8) as u8);
+        self.data.push(value as u8);
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write_u16((value >> 16) as u16);
+        self.write_u16(value as u16);
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn write_count(&mut self, count: usize) -> Result<(), AresError> {
+        if count > 0xffff {
+            return Err(AresError::EBADQUERY)
+        }
+        self.write_u16(count as u16);
+        Ok(())
+    }
+
+    // Write `name`, pointing back to an earlier copy of as much of it as
+    // possible.
+    fn write_name(&mut self, name: &str) -> Result<(), AresError> {
+        let labels = parse_name(name)?;
+        for index in 0..labels.len() {
+            // Only point back to an exact match, so that the case of every
+            // name is preserved.
+            let suffix: Vec<u8> = labels[index..]
+                .iter()
+                .flat_map(|label| {
+                    Some(label.len() as u8)
+                        .into_iter()
+                        .chain(label.iter().cloned())
+                })
+                .collect();
+            if let Some(&offset) = self.names.get(&suffix) {
+                self.write_u16(0xc000 | offset as u16);
+                return Ok(())
+            }
+            let offset = self.data.len();
+            if offset < 0x4000 {
+                self.names.insert(suffix, offset);
+            }
+            self.data.push(labels[index].len() as u8);
+            self.data.extend_from_slice(&labels[index]);
+        }
+        self.data.push(0);
+        Ok(())
+    }
+}
+
+// Checks that `name` is a valid domain name, in presentation format.
+pub(crate) fn check_name(name: &str) -> Result<(), AresError> {
+    parse_name(name).map(|_| ())
+}
+
+// Split a name in presentation format into its labels, undoing any escapes.
+// The root name may be written as either "" or ".".
+fn parse_name(name: &str) -> Result<Vec<Vec<u8>>, AresError> {
+    let mut labels = Vec::new();
+    if name == "." || name.is_empty() {
+        return Ok(labels)
+    }
+    let mut label = Vec::new();
+    let mut bytes = name.bytes();
+    let mut length = 1;
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'.' => {
+                if label.is_empty() {
+                    return Err(AresError::EBADNAME)
+                }
+                length = length + 1 + label.len();
+                labels.push(label);
+                label = Vec::new();
+                continue
+            },
+            b'\\' => {
+                let escaped = bytes.next().ok_or(AresError::EBADNAME)?;
+                if escaped.is_ascii_digit() {
+                    let mut value = (escaped - b'0') as u32;
+                    for _ in 0..2 {
+                        let digit = bytes.next().ok_or(AresError::EBADNAME)?;
+                        if !digit.is_ascii_digit() {
+                            return Err(AresError::EBADNAME)
+                        }
+                        value = value * 10 + (digit - b'0') as u32;
+                    }
+                    if value > 0xff {
+                        return Err(AresError::EBADNAME)
+                    }
+                    label.push(value as u8);
+                } else {
+                    label.push(escaped);
+                }
+            },
+            _ => label.push(byte),
+        }
+        if label.len() > MAX_LABEL_LENGTH {
+            return Err(AresError::EBADNAME)
+        }
+    }
+    if !label.is_empty() {
+        length = length + 1 + label.len();
+        labels.push(label);
+    }
+    if length > MAX_NAME_LENGTH {
+        return Err(AresError::EBADNAME)
+    }
+    Ok(labels)
+}
+
+// Reads values from DNS wire format.  All failures are reported as
+// `EBADRESP`.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader {
+            data: data,
+            pos: 0,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8, AresError> {
+        self.read_bytes(1).map(|bytes| bytes[0])
+    }
+
+    pub(crate) fn read_u16(&mut self) -> Result<u16, AresError> {
+        self.read_bytes(2)
+            .map(|bytes| ((bytes[0] as u16) << 8) | bytes[1] as u16)
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32, AresError> {
+        self.read_bytes(4).map(|bytes| {
+            bytes.iter().fold(0, |value, &byte| (value << 8) | byte as u32)
+        })
+    }
+
+    pub(crate) fn read_bytes(
+        &mut self,
+        length: usize) -> Result<&'a [u8], AresError> {
+        if self.data.len() - self.pos < length {
+            return Err(AresError::EBADRESP)
+        }
+        let bytes = &self.data[self.pos..self.pos + length];
+        self.pos = self.pos + length;
+        Ok(bytes)
+    }
+
+    // Read whatever remains.
+    pub(crate) fn read_rest(&mut self) -> &'a [u8] {
+        let bytes = &self.data[self.pos..];
+        self.pos = self.data.len();
+        bytes
+    }
+
+    // Read a length-prefixed character-string.
+    pub(crate) fn read_string(&mut self) -> Result<&'a [u8], AresError> {
+        let length = self.read_u8()?;
+        self.read_bytes(length as usize)
+    }
+
+    // Read a type bitmap, as used by NSEC and NSEC3 records, from the rest of
+    // the data.  Returns the types that it lists, in increasing order.
+    pub(crate) fn read_type_bitmap(&mut self) -> Result<Vec<u16>, AresError> {
+        let mut types = Vec::new();
+        while !self.is_empty() {
+            let window = self.read_u8()?;
+            let length = self.read_u8()?;
+            if length == 0 || length > 32 {
+                return Err(AresError::EBADRESP)
+            }
+            let bitmap = self.read_bytes(length as usize)?;
+            for (index, &byte) in bitmap.iter().enumerate() {
+                for bit in 0..8 {
+                    if byte & (0x80 >> bit) != 0 {
+                        let rr_type = ((window as u16) << 8) |
+                            (index as u16 * 8 + bit);
+                        types.push(rr_type);
+                    }
+                }
+            }
+        }
+        Ok(types)
+    }
+
+    // Read a possibly compressed name, and return it in presentation format.
+    pub(crate) fn read_name(&mut self) -> Result<String, AresError> {
+        self.read_name_with(true)
+    }
+
+    // Read a name that must not be compressed - as in the data of most record
+    // types defined since RFC 3597 - and return it in presentation format.
+    pub(crate) fn read_uncompressed_name(
+        &mut self) -> Result<String, AresError> {
+        self.read_name_with(false)
+    }
+
+    fn read_name_with(
+        &mut self,
+        compressed: bool) -> Result<String, AresError> {
+        let mut name = String::new();
+        let mut pos = self.pos;
+        let mut end = None;
+        let mut pointers = 0;
+        let mut length = 0;
+        loop {
+            let label_length = *self.data
+                .get(pos)
+                .ok_or(AresError::EBADRESP)?;
+            match label_length & 0xc0 {
+                0x00 => {
+                    if label_length == 0 {
+                        break
+                    }
+                    let start = pos + 1;
+                    let label = self.data
+                        .get(start..start + label_length as usize)
+                        .ok_or(AresError::EBADRESP)?;
+                    length = length + 1 + label.len();
+                    if length + 1 > MAX_NAME_LENGTH {
+                        return Err(AresError::EBADRESP)
+                    }
+                    if !name.is_empty() {
+                        name.push('.');
+                    }
+                    escape_label(label, &mut name);
+                    pos = start + label.len();
+                },
+                0xc0 if compressed => {
+                    let low = *self.data
+                        .get(pos + 1)
+                        .ok_or(AresError::EBADRESP)?;
+                    if end.is_none() {
+                        end = Some(pos + 2);
+                    }
+                    pointers = pointers + 1;
+                    if pointers > MAX_POINTERS {
+                        return Err(AresError::EBADRESP)
+                    }
+                    pos = (((label_length & 0x3f) as usize) << 8) |
+                        low as usize;
+                },
+                _ => return Err(AresError::EBADRESP),
+            }
+        }
+        self.pos = end.unwrap_or(pos + 1);
+        Ok(name)
+    }
+}
+
+// Append `label` to `name`, escaping it for presentation format.
+fn escape_label(label: &[u8], name: &mut String) {
+    for &byte in label {
+        match byte {
+            b'.' | b'\\' => {
+                name.push('\\');
+                name.push(byte as char);
+            },
+            0x21..=0x7e => name.push(byte as char),
+            _ => name.push_str(&format!("\\{:03}", byte)),
+        }
+    }
+}
+
+// Builds a response to a query for records of type `rr_type`, with an answer
+// for each of `rdatas`.  For testing the parsers of particular record types.
+#[cfg(test)]
+pub(crate) fn test_response(rr_type: QueryType, rdatas: &[&[u8]]) -> Vec<u8> {
+    let mut builder = MessageBuilder::new();
+    builder
+        .set_response(true)
+        .add_question("example.com", rr_type as u16, 1);
+    for rdata in rdatas {
+        builder.add_answer("example.com", rr_type as u16, 1, 300, rdata);
+    }
+    builder.build().unwrap()
+}
+
+// Parses, with `parse_from`, a response built by `test_response()`.
+#[cfg(test)]
+pub(crate) fn parse_test_response<T>(
+    parse_from: fn(&[u8]) -> Result<T, AresError>,
+    rr_type: QueryType,
+    rdatas: &[&[u8]]) -> Result<T, AresError> {
+    parse_from(&test_response(rr_type, rdatas))
+}
+
+// Checks that `result` is the failure to parse a malformed response.
+#[cfg(test)]
+pub(crate) fn assert_bad_response<T>(result: Result<T, AresError>) {
+    match result {
+        Err(AresError::EBADRESP) => (),
+        Err(e) => panic!("unexpected error {:?}", e),
+        Ok(_) => panic!("unexpected success"),
+    }
+}
+
+// Checks that `parse_from` rejects each of `rdatas`, as the only answer in a
+// response.
+#[cfg(test)]
+pub(crate) fn assert_rejects_rdata<T>(
+    parse_from: fn(&[u8]) -> Result<T, AresError>,
+    rr_type: QueryType,
+    rdatas: &[&[u8]]) {
+    for &rdata in rdatas {
+        assert_bad_response(parse_test_response(parse_from, rr_type, &[rdata]));
+    }
+}
+
+// Checks that `parse_from` rejects a response in which `rdata` is cut short,
+// so that the record claims more data than the message holds.
+#[cfg(test)]
+pub(crate) fn assert_rejects_overlong_rdata<T>(
+    parse_from: fn(&[u8]) -> Result<T, AresError>,
+    rr_type: QueryType,
+    rdata: &[u8]) {
+    let mut data = test_response(rr_type, &[rdata]);
+    data.pop();
+    assert_bad_response(parse_from(&data));
+}
+
+#[cfg(test)]
+mod tests {
+    use error::AresError;
+    use super::{
+        MAX_POINTERS,
+        Message,
+        MessageBuilder,
+        Reader,
+        assert_bad_response,
+    };
+
+    // A header with the given ID, flags and section counts.
+    fn header(id: u16, flags: u16, counts: [u16; 4]) -> Vec<u8> {
+        let mut data = vec![(id >> 8) as u8, id as u8];
+        data.push((flags >> 8) as u8);
+        data.push(flags as u8);
+        for &count in &counts {
+            data.push((count >> 8) as u8);
+            data.push(count as u8);
+        }
+        data
+    }
+
+    // A response to an A query for www.example.com, with one answer whose
+    // name is compressed.
+    fn example_response() -> Vec<u8> {
+        let mut data = header(0x1234, 0x8180, [1, 1, 0, 0]);
+        data.extend_from_slice(b"\x03www\x07example\x03com\x00");
+        data.extend_from_slice(&[0, 1, 0, 1]);
+        data.extend_from_slice(&[0xc0, 12]);
+        data.extend_from_slice(&[0, 1, 0, 1]);
+        data.extend_from_slice(&[0, 0, 0x0e, 0x10]);
+        data.extend_from_slice(&[0, 4, 192, 0, 2, 1]);
+        data
+    }
+
+    #[test]
+    fn parses_response() {
+        let message = Message::parse(&example_response()).unwrap();
+        let header = message.header();
+        assert_eq!(header.id(), 0x1234);
+        assert!(header.is_response());
+        assert!(header.recursion_desired());
+        assert!(header.recursion_available());
+        assert!(!header.authoritative());
+        assert_eq!(message.rcode(), 0);
+
+        assert_eq!(message.questions().len(), 1);
+        let question = &message.questions()[0];
+        assert_eq!(question.name(), "www.example.com");
+        assert_eq!(question.query_type(), 1);
+        assert_eq!(question.dns_class(), 1);
+
+        assert_eq!(message.answers().len(), 1);
+        let answer = &message.answers()[0];
+        assert_eq!(answer.name(), "www.example.com");
+        assert_eq!(answer.rr_type(), 1);
+        assert_eq!(answer.ttl(), 3600);
+        assert_eq!(answer.rdata(), &[192, 0, 2, 1]);
+        assert!(message.authority().is_empty());
+        assert!(message.additional().is_empty());
+        assert!(message.opt().is_none());
+    }
+
+    #[test]
+    fn parses_opt_record() {
+        let mut data = header(0, 0x8003, [0, 0, 0, 1]);
+        data.push(0);
+        data.extend_from_slice(&[0, 41, 0x04, 0xd0]);
+        data.extend_from_slice(&[0x01, 0x00, 0x80, 0x00]);
+        data.extend_from_slice(&[0, 6, 0, 10, 0, 2, 0xab, 0xcd]);
+        let message = Message::parse(&data).unwrap();
+        assert!(message.additional().is_empty());
+        let opt = message.opt().unwrap();
+        assert_eq!(opt.udp_payload_size(), 1232);
+        assert_eq!(opt.extended_rcode(), 1);
+        assert_eq!(opt.version(), 0);
+        assert!(opt.dnssec_ok());
+        assert_eq!(opt.options().len(), 1);
+        assert_eq!(opt.options()[0].code(), 10);
+        assert_eq!(opt.options()[0].data(), &[0xab, 0xcd]);
+        assert_eq!(message.rcode(), 0x13);
+    }
+
+    #[test]
+    fn rejects_truncated_messages() {
+        let data = example_response();
+        for length in 0..data.len() {
+            assert_bad_response(Message::parse(&data[..length]));
+        }
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        let mut data = example_response();
+        let length = data.len();
+        data[length - 5] = 5;
+        assert_bad_response(Message::parse(&data));
+    }
+
+    #[test]
+    fn rejects_pointer_loops() {
+        let mut data = header(0, 0, [1, 0, 0, 0]);
+        data.extend_from_slice(&[0xc0, 12]);
+        data.extend_from_slice(&[0, 1, 0, 1]);
+        assert_bad_response(Message::parse(&data));
+    }
+
+    // A name that is reached through `pointers` compression pointers.
+    fn pointer_chain(pointers: usize) -> Vec<u8> {
+        let mut data = b"\x07example\x00".to_vec();
+        let mut target = 0;
+        for _ in 0..pointers {
+            let offset = data.len();
+            data.push(0xc0 | (target >> 8) as u8);
+            data.push(target as u8);
+            target = offset;
+        }
+        data
+    }
+
+    #[test]
+    fn limits_pointer_chains() {
+        let data = pointer_chain(MAX_POINTERS);
+        let mut reader = Reader::new(&data);
+        reader.pos = data.len() - 2;
+        assert_eq!(reader.read_name().unwrap(), "example");
+        assert!(reader.is_empty());
+
+        let data = pointer_chain(MAX_POINTERS + 1);
+        let mut reader = Reader::new(&data);
+        reader.pos = data.len() - 2;
+        assert_bad_response(reader.read_name());
+    }
+
+    #[test]
+    fn rejects_reserved_label_types() {
+        for &label_type in &[0x40, 0x80] {
+            let data = [label_type | 1, b'a', 0];
+            assert_bad_response(Reader::new(&data).read_name());
+        }
+    }
+
+    #[test]
+    fn limits_name_length() {
+        // 127 one-byte labels make a name of 255 bytes on the wire.
+        let mut data = Vec::new();
+        for _ in 0..127 {
+            data.extend_from_slice(b"\x01a");
+        }
+        data.push(0);
+        let name = Reader::new(&data).read_name().unwrap();
+        assert_eq!(name.len(), 253);
+
+        let mut long = Vec::new();
+        for _ in 0..128 {
+            long.extend_from_slice(b"\x01a");
+        }
+        long.push(0);
+        assert_bad_response(Reader::new(&long).read_name());
+    }
+
+    #[test]
+    fn escapes_names() {
+        let data = b"\x04a.b\\\x02\x00\xff\x00";
+        let name = Reader::new(data).read_name().unwrap();
+        assert_eq!(name, "a\\.b\\\\.\\000\\255");
+    }
+
+    #[test]
+    fn reads_type_bitmaps() {
+        let data = [0, 1, 0x40, 1, 1, 0x40];
+        let types = Reader::new(&data).read_type_bitmap().unwrap();
+        assert_eq!(types, vec![1, 257]);
+
+        assert_bad_response(Reader::new(&[0, 0]).read_type_bitmap());
+        assert_bad_response(Reader::new(&[0, 33]).read_type_bitmap());
+        assert_bad_response(Reader::new(&[0, 2, 0x40]).read_type_bitmap());
+    }
+
+    #[test]
+    fn builds_round_trip() {
+        let data = MessageBuilder::new()
+            .set_id(0xbeef)
+            .set_response(true)
+            .set_opcode(2)
+            .set_authoritative(true)
+            .set_recursion_desired(true)
+            .set_authentic_data(true)
+            .set_rcode(0x15)
+            .set_edns(4096)
+            .set_dnssec_ok(true)
+            .add_edns_option(8, &[1, 2, 3])
+            .add_question("www.example.com", 16, 1)
+            .add_answer("www.example.com", 16, 1, 300, b"\x02hi")
+            .add_authority("example.com", 2, 1, 60, b"\x02ns\x00")
+            .add_additional("ns", 1, 1, 60, &[192, 0, 2, 53])
+            .build()
+            .unwrap();
+        let message = Message::parse(&data).unwrap();
+        let header = message.header();
+        assert_eq!(header.id(), 0xbeef);
+        assert!(header.is_response());
+        assert_eq!(header.opcode(), 2);
+        assert!(header.authoritative());
+        assert!(header.recursion_desired());
+        assert!(!header.recursion_available());
+        assert!(header.authentic_data());
+        assert!(!header.checking_disabled());
+        assert!(!header.truncated());
+        assert_eq!(message.rcode(), 0x15);
+
+        assert_eq!(message.questions()[0].name(), "www.example.com");
+        assert_eq!(message.questions()[0].query_type(), 16);
+        let answer = &message.answers()[0];
+        assert_eq!(answer.name(), "www.example.com");
+        assert_eq!(answer.ttl(), 300);
+        assert_eq!(answer.rdata(), b"\x02hi");
+        assert_eq!(message.authority()[0].name(), "example.com");
+        assert_eq!(message.additional().len(), 1);
+        assert_eq!(message.additional()[0].name(), "ns");
+
+        let opt = message.opt().unwrap();
+        assert_eq!(opt.udp_payload_size(), 4096);
+        assert!(opt.dnssec_ok());
+        assert_eq!(opt.options()[0].code(), 8);
+        assert_eq!(opt.options()[0].data(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn compresses_names() {
+        let data = MessageBuilder::new()
+            .add_question("www.example.com", 1, 1)
+            .add_answer("www.example.com", 1, 1, 0, &[])
+            .add_answer("mail.example.com", 1, 1, 0, &[])
+            .build()
+            .unwrap();
+
+        // The first answer is just a pointer to the question, and the second
+        // points back to "example.com".
+        assert_eq!(&data[33..35], &[0xc0, 12]);
+        assert_eq!(&data[45..52], b"\x04mail\xc0\x10");
+
+        let message = Message::parse(&data).unwrap();
+        assert_eq!(message.answers()[0].name(), "www.example.com");
+        assert_eq!(message.answers()[1].name(), "mail.example.com");
+    }
+
+    #[test]
+    fn preserves_case_when_compressing() {
+        let data = MessageBuilder::new()
+            .add_question("www.example.com", 1, 1)
+            .add_answer("WWW.Example.com", 1, 1, 0, &[])
+            .add_answer("www.EXAMPLE.COM", 1, 1, 0, &[])
+            .build()
+            .unwrap();
+        let message = Message::parse(&data).unwrap();
+        assert_eq!(message.questions()[0].name(), "www.example.com");
+        assert_eq!(message.answers()[0].name(), "WWW.Example.com");
+        assert_eq!(message.answers()[1].name(), "www.EXAMPLE.COM");
+    }
+
+    #[test]
+    fn round_trips_escaped_names() {
+        let name = "a\\.b\\\\.\\000\\255";
+        let data = MessageBuilder::new()
+            .add_question(name, 1, 1)
+            .build()
+            .unwrap();
+        let message = Message::parse(&data).unwrap();
+        assert_eq!(message.questions()[0].name(), name);
+    }
+
+    #[test]
+    fn builds_root_name() {
+        for &name in &["", "."] {
+            let data = MessageBuilder::new()
+                .add_question(name, 2, 1)
+                .build()
+                .unwrap();
+            assert_eq!(&data[12..], &[0, 0, 2, 0, 1]);
+            let message = Message::parse(&data).unwrap();
+            assert_eq!(message.questions()[0].name(), "");
+        }
+    }
+
+    fn assert_bad_name(name: &str) {
+        let result = MessageBuilder::new().add_question(name, 1, 1).build();
+        match result {
+            Err(AresError::EBADNAME) => (),
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("unexpected success for {:?}", name),
+        }
+    }
+
+    #[test]
+    fn limits_label_length() {
+        let label = "a".repeat(63);
+        MessageBuilder::new()
+            .add_question(&label, 1, 1)
+            .build()
+            .unwrap();
+        assert_bad_name(&"a".repeat(64));
+        assert_bad_name(&format!("{}.com", "a".repeat(64)));
+    }
+
+    #[test]
+    fn limits_built_name_length() {
+        // 127 one-byte labels make a name of 255 bytes on the wire.
+        let name = vec!["a"; 127].join(".");
+        MessageBuilder::new()
+            .add_question(&name, 1, 1)
+            .build()
+            .unwrap();
+        assert_bad_name(&format!("{}.a", name));
+        assert_bad_name(&format!("{}b", name));
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        assert_bad_name("a..b");
+        assert_bad_name(".a");
+        assert_bad_name("a\\");
+        assert_bad_name("a\\25");
+        assert_bad_name("a\\2x5");
+        assert_bad_name("a\\256");
+    }
+
+    #[test]
+    fn needs_edns_for_extended_fields() {
+        let mut builders = vec![MessageBuilder::new(); 3];
+        builders[0].set_rcode(16);
+        builders[1].set_dnssec_ok(true);
+        builders[2].add_edns_option(1, &[]);
+        for builder in &mut builders {
+            match builder.build() {
+                Err(AresError::EBADQUERY) => (),
+                _ => panic!("built without EDNS"),
+            }
+            builder.set_edns(512);
+            builder.build().unwrap();
+        }
+
+        let mut builder = MessageBuilder::new();
+        builder.set_edns(512).set_rcode(0x1000);
+        match builder.build() {
+            Err(AresError::EBADQUERY) => (),
+            _ => panic!("built with oversized rcode"),
+        }
+    }
+
+    #[test]
+    fn reads_uncompressed_names() {
+        let data = b"\x07example\x00\x03www\xc0\x00";
+        let mut reader = Reader::new(data);
+        assert_eq!(reader.read_uncompressed_name().unwrap(), "example");
+        assert_bad_response(reader.read_uncompressed_name());
+
+        let mut reader = Reader::new(data);
+        reader.pos = 9;
+        assert_eq!(reader.read_name().unwrap(), "www.example");
+    }
+}
@@ -0,0 +1,241 @@
+//! Integration with the [`mio`](https://github.com/tokio-rs/mio) event
+//! library.
+//!
+//! This module is available only if the `mio` feature is enabled.
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::os::unix::io::{
+    AsRawFd,
+    RawFd,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::Duration;
+
+use mio_crate::{
+    Events,
+    Interest,
+    Poll,
+    Registry,
+    Token,
+};
+use mio_crate::event::Source;
+use mio_crate::unix::SourceFd;
+
+use channel::{
+    Channel,
+    Options,
+};
+use error::AresError;
+use poll::poll_fds;
+use types::INVALID_FD;
+
+// The most times that `process_events()` will go back to sockets that are
+// still ready, before leaving them for the next call.
+const MAX_PASSES: usize = 16;
+
+/// Drives a `Channel` from a `mio` event loop.
+///
+/// The `MioEventDriver` keeps track of the sockets that `c-ares` is
+/// interested in, and is itself a `mio::event::Source`: register it with your
+/// `mio::Poll` for readable events.  Then, in your event loop:
+///
+/// -  Use `timeout()` to limit how long you wait in `Poll::poll()`.
+///
+/// -  Whenever the driver's token is ready, or the timeout expires, call
+///    `process_events()`.
+///
+/// Make queries on the `Channel` returned by `channel()`.
+pub struct MioEventDriver {
+    channel: Channel,
+    poll: Poll,
+    events: Events,
+    interests: Arc<Mutex<HashMap<RawFd, (bool, bool)>>>,
+
+    // Sockets that were still ready when `process_events()` last gave up.
+    still_ready: Vec<(RawFd, bool, bool)>,
+}
+
+impl MioEventDriver {
+    /// Create a new `MioEventDriver`, using the given `Options`.
+    ///
+    /// The driver needs to know when `c-ares` changes its interest in
+    /// sockets, so this replaces any socket state callback that has been set
+    /// on `options`.
+    pub fn new(mut options: Options) -> Result<MioEventDriver, AresError> {
+        let poll = match Poll::new() {
+            Ok(poll) => poll,
+            Err(_) => return Err(AresError::UNKNOWN),
+        };
+        let registry = match poll.registry().try_clone() {
+            Ok(registry) => registry,
+            Err(_) => return Err(AresError::UNKNOWN),
+        };
+        let interests = Arc::new(Mutex::new(HashMap::new()));
+        let interests_clone = interests.clone();
+        options.set_socket_state_callback(move |fd, readable, writable| {
+            let mut interests = interests_clone.lock().unwrap();
+            register_interest(
+                &registry,
+                &mut interests,
+                fd,
+                readable,
+                writable);
+        });
+        let channel = Channel::new(options)?;
+        let driver = MioEventDriver {
+            channel: channel,
+            poll: poll,
+            events: Events::with_capacity(16),
+            interests: interests,
+            still_ready: Vec::new(),
+        };
+        Ok(driver)
+    }
+
+    /// Returns the `Channel` that this `MioEventDriver` drives.  Make queries
+    /// on this.
+    pub fn channel(&mut self) -> &mut Channel {
+        &mut self.channel
+    }
+
+    /// Returns the longest that the event loop should wait before calling
+    /// `process_events()`, even if nothing else happens - or `None` if there
+    /// is no limit.
+    ///
+    /// This is zero if the last call to `process_events()` left work to do.
+    pub fn timeout(&self) -> Option<Duration> {
+        if self.still_ready.is_empty() {
+            self.channel.timeout(None)
+        } else {
+            Some(Duration::from_secs(0))
+        }
+    }
+
+    /// Tell `c-ares` about any of its sockets that have become readable or
+    /// writable, and give it the chance to handle any queries that have timed
+    /// out.
+    ///
+    /// So that timeouts are never starved, this does a bounded amount of
+    /// work.  If sockets are still ready when it returns, `timeout()` is zero.
+    pub fn process_events(&mut self) {
+        // Collect everything that's ready, starting with anything left over
+        // from last time.  If a poll fills `events` then there may be more to
+        // come, so poll again until one doesn't.
+        let mut ready = mem::replace(&mut self.still_ready, Vec::new());
+        loop {
+            let zero = Some(Duration::from_secs(0));
+            match self.poll.poll(&mut self.events, zero) {
+                Ok(()) => (),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {
+                    continue
+                },
+                Err(_) => break,
+            }
+            let mut count = 0;
+            for event in self.events.iter() {
+                count = count + 1;
+                let fd = event.token().0 as RawFd;
+                let readable = event.is_readable() ||
+                    event.is_read_closed() ||
+                    event.is_error();
+                let writable = event.is_writable();
+                match ready.iter_mut().find(|&&mut (f, _, _)| f == fd) {
+                    Some(entry) => {
+                        entry.1 = entry.1 || readable;
+                        entry.2 = entry.2 || writable;
+                    },
+                    None => ready.push((fd, readable, writable)),
+                }
+            }
+            if count < self.events.capacity() {
+                break
+            }
+        }
+
+        // mio is edge-triggered, and c-ares doesn't promise to drain the
+        // sockets that it's told about.  So keep going back to sockets that
+        // are still ready, and that c-ares is still interested in, until
+        // there are none left - or until we've done enough for now, in which
+        // case we remember the rest for next time.
+        for _ in 0..MAX_PASSES {
+            if ready.is_empty() {
+                break
+            }
+            for &(fd, readable, writable) in &ready {
+                let read_fd = if readable { fd } else { INVALID_FD };
+                let write_fd = if writable { fd } else { INVALID_FD };
+                self.channel.process_fd(read_fd, write_fd);
+            }
+            let interested: Vec<_> = {
+                let interests = self.interests.lock().unwrap();
+                ready
+                    .iter()
+                    .filter_map(|&(fd, _, _)| {
+                        interests.get(&fd).map(|&(r, w)| (fd, r, w))
+                    })
+                    .collect()
+            };
+            ready = poll_fds(&interested, Some(Duration::from_secs(0)));
+        }
+        self.still_ready = ready;
+
+        self.channel.process_timeouts();
+    }
+}
+
+impl Source for MioEventDriver {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest) -> io::Result<()> {
+        SourceFd(&self.poll.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest) -> io::Result<()> {
+        SourceFd(&self.poll.as_raw_fd())
+            .reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.poll.as_raw_fd()).deregister(registry)
+    }
+}
+
+// Update the registration of `fd` to match what c-ares is interested in.
+fn register_interest(
+    registry: &Registry,
+    interests: &mut HashMap<RawFd, (bool, bool)>,
+    fd: RawFd,
+    readable: bool,
+    writable: bool) {
+    let mut source = SourceFd(&fd);
+    let interest = match (readable, writable) {
+        (false, false) => {
+            if interests.remove(&fd).is_some() {
+                let _ = registry.deregister(&mut source);
+            }
+            return
+        },
+        (true, false) => Interest::READABLE,
+        (false, true) => Interest::WRITABLE,
+        (true, true) => Interest::READABLE | Interest::WRITABLE,
+    };
+    let token = Token(fd as usize);
+    let result = if interests.insert(fd, (readable, writable)).is_some() {
+        registry.reregister(&mut source, token, interest)
+    } else {
+        registry.register(&mut source, token, interest)
+    };
+    if result.is_err() {
+        interests.remove(&fd);
+    }
+}
@@ -1,14 +1,15 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::borrow::Cow;
 use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::mem;
 use std::ptr;
 use std::slice;
-use std::str;
 
 use error::AresError;
+use unwind;
 use utils::ares_error;
 
 /// The result of a successful MX lookup.
@@ -23,6 +24,20 @@ pub struct MXResult<'a> {
     phantom: PhantomData<&'a c_ares_sys::Struct_ares_mx_reply>,
 }
 
+/// An owned copy of a single MX record, as returned by
+/// `MXResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedMXResult {
+    /// The hostname.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub host: String,
+
+    /// The hostname, exactly as received.
+    pub host_bytes: Vec<u8>,
+
+    /// The priority.
+    pub priority: u16,
+}
+
 impl MXResults {
     /// Obtain an `MXResults` from the response to an MX lookup.
     pub fn parse_from(data: &[u8]) -> Result<MXResults, AresError> {
@@ -55,6 +70,12 @@ impl MXResults {
             phantom: PhantomData,
         }
     }
+
+    /// Returns owned copies of the `MXResult` values in this `MXResults`,
+    /// which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedMXResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
 }
 
 pub struct MXResultsIterator<'a> {
@@ -105,11 +126,17 @@ unsafe impl<'a> Send for MXResultsIterator<'a> { }
 unsafe impl<'a> Sync for MXResultsIterator<'a> { }
 
 impl<'a> MXResult<'a> {
-    /// Returns the hostname in this `MXResult`.
-    pub fn host(&self) -> &str {
+    /// Returns the hostname in this `MXResult`.  Any invalid UTF-8 is replaced
+    /// by U+FFFD.
+    pub fn host(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.host_bytes())
+    }
+
+    /// Returns the hostname in this `MXResult`, exactly as received.
+    pub fn host_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr((*self.mx_reply).host);
-            str::from_utf8_unchecked(c_str.to_bytes())
+            c_str.to_bytes()
         }
     }
 
@@ -117,6 +144,15 @@ impl<'a> MXResult<'a> {
     pub fn priority(&self) -> u16 {
         unsafe { (*self.mx_reply).priority }
     }
+
+    /// Returns an owned copy of this `MXResult`.
+    pub fn to_owned(&self) -> OwnedMXResult {
+        OwnedMXResult {
+            host: self.host().into_owned(),
+            host_bytes: self.host_bytes().to_vec(),
+            priority: self.priority(),
+        }
+    }
 }
 
 pub unsafe extern "C" fn query_mx_callback<F>(
@@ -126,12 +162,14 @@ pub unsafe extern "C" fn query_mx_callback<F>(
     abuf: *mut libc::c_uchar,
     alen: libc::c_int)
     where F: FnOnce(Result<MXResults, AresError>) + 'static {
-    let handler: Box<F> = mem::transmute(arg);
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let data = slice::from_raw_parts(abuf, alen as usize);
-        MXResults::parse_from(data)
-    };
-    handler(result);
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            MXResults::parse_from(data)
+        };
+        handler(result);
+    });
 }
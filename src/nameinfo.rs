@@ -1,12 +1,13 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::borrow::Cow;
 use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::mem;
-use std::str;
 
 use error::AresError;
+use unwind;
 use utils::ares_error;
 
 /// The result of a successful name-info lookup.
@@ -16,6 +17,24 @@ pub struct NameInfoResult<'a> {
     phantom: PhantomData<&'a libc::c_char>,
 }
 
+/// An owned copy of a `NameInfoResult`, as returned by
+/// `NameInfoResult::to_owned()`.  Unlike a `NameInfoResult`, this can outlive
+/// the callback to which the result was passed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedNameInfoResult {
+    /// The node.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub node: Option<String>,
+
+    /// The node, exactly as received.
+    pub node_bytes: Option<Vec<u8>>,
+
+    /// The service.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub service: Option<String>,
+
+    /// The service, exactly as received.
+    pub service_bytes: Option<Vec<u8>>,
+}
+
 impl<'a> NameInfoResult<'a> {
     fn new(
         node: *const libc::c_char,
@@ -27,29 +46,52 @@ impl<'a> NameInfoResult<'a> {
         }
     }
 
-    /// Returns the node from this `NameInfoResult`.
-    pub fn node(&self) -> Option<&str> {
+    /// Returns the node from this `NameInfoResult`.  Any invalid UTF-8 is
+    /// replaced by U+FFFD.
+    pub fn node(&self) -> Option<Cow<str>> {
+        self.node_bytes().map(String::from_utf8_lossy)
+    }
+
+    /// Returns the node from this `NameInfoResult`, exactly as received.
+    pub fn node_bytes(&self) -> Option<&[u8]> {
         if self.node.is_null() {
             None
         } else {
             unsafe {
                 let c_str = CStr::from_ptr(self.node);
-                Some(str::from_utf8_unchecked(c_str.to_bytes()))
+                Some(c_str.to_bytes())
             }
         }
     }
 
-    /// Returns the service from this `NameInfoResult`.
-    pub fn service(&self) -> Option<&str> {
+    /// Returns the service from this `NameInfoResult`.  Any invalid UTF-8 is
+    /// replaced by U+FFFD.
+    pub fn service(&self) -> Option<Cow<str>> {
+        self.service_bytes().map(String::from_utf8_lossy)
+    }
+
+    /// Returns the service from this `NameInfoResult`, exactly as received.
+    pub fn service_bytes(&self) -> Option<&[u8]> {
         if self.service.is_null() {
             None
         } else {
             unsafe {
                 let c_str = CStr::from_ptr(self.service);
-                Some(str::from_utf8_unchecked(c_str.to_bytes()))
+                Some(c_str.to_bytes())
             }
         }
     }
+
+    /// Returns an owned copy of this `NameInfoResult`.
+    pub fn to_owned(&self) -> OwnedNameInfoResult {
+        OwnedNameInfoResult {
+            node: self.node().map(|node| node.into_owned()),
+            node_bytes: self.node_bytes().map(|node| node.to_vec()),
+            service: self.service().map(|service| service.into_owned()),
+            service_bytes: self.service_bytes()
+                .map(|service| service.to_vec()),
+        }
+    }
 }
 
 unsafe impl<'a> Send for NameInfoResult<'a> { }
@@ -62,12 +104,14 @@ pub unsafe extern "C" fn get_name_info_callback<F>(
     node: *mut libc::c_char,
     service: *mut libc::c_char)
     where F: FnOnce(Result<NameInfoResult, AresError>) + 'static {
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let name_info_result = NameInfoResult::new(node, service);
-        Ok(name_info_result)
-    };
-    let handler: Box<F> = mem::transmute(arg);
-    handler(result);
+    unwind::catch(|| {
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let name_info_result = NameInfoResult::new(node, service);
+            Ok(name_info_result)
+        };
+        let handler: Box<F> = mem::transmute(arg);
+        handler(result);
+    });
 }
@@ -1,14 +1,15 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::borrow::Cow;
 use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::mem;
-use std::str;
 use std::ptr;
 use std::slice;
 
 use error::AresError;
+use unwind;
 use utils::ares_error;
 
 /// The result of a successful NAPTR lookup.
@@ -23,6 +24,41 @@ pub struct NAPTRResult<'a> {
     phantom: PhantomData<&'a c_ares_sys::Struct_ares_naptr_reply>,
 }
 
+/// An owned copy of a single NAPTR record, as returned by
+/// `NAPTRResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedNAPTRResult {
+    /// The flags.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub flags: String,
+
+    /// The flags, exactly as received.
+    pub flags_bytes: Vec<u8>,
+
+    /// The service name.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub service_name: String,
+
+    /// The service name, exactly as received.
+    pub service_name_bytes: Vec<u8>,
+
+    /// The regular expression.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub reg_exp: String,
+
+    /// The regular expression, exactly as received.
+    pub reg_exp_bytes: Vec<u8>,
+
+    /// The replacement pattern.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub replacement_pattern: String,
+
+    /// The replacement pattern, exactly as received.
+    pub replacement_pattern_bytes: Vec<u8>,
+
+    /// The order value.
+    pub order: u16,
+
+    /// The preference value.
+    pub preference: u16,
+}
+
 impl NAPTRResults {
     #[cfg(feature = "old-cares")]
     pub fn parse_from(_data: &[u8]) -> Result<NAPTRResults, AresError> {
@@ -64,6 +100,12 @@ impl NAPTRResults {
             phantom: PhantomData,
         }
     }
+
+    /// Returns owned copies of the `NAPTRResult` values in this `NAPTRResults`,
+    /// which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedNAPTRResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
 }
 
 pub struct NAPTRResultsIterator<'a> {
@@ -113,38 +155,64 @@ unsafe impl<'a> Send for NAPTRResultsIterator<'a> { }
 unsafe impl<'a> Sync for NAPTRResultsIterator<'a> { }
 
 impl<'a> NAPTRResult<'a> {
-    /// Returns the flags in this `NAPTRResult`.
-    pub fn flags(&self) -> &str {
+    /// Returns the flags in this `NAPTRResult`.  Any invalid UTF-8 is replaced
+    /// by U+FFFD.
+    pub fn flags(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.flags_bytes())
+    }
+
+    /// Returns the flags in this `NAPTRResult`, exactly as received.
+    pub fn flags_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr(
                 (*self.naptr_reply).flags as *const libc::c_char);
-            str::from_utf8(c_str.to_bytes()).unwrap()
+            c_str.to_bytes()
         }
     }
 
-    /// Returns the service name in this `NAPTRResult`.
-    pub fn service_name(&self) -> &str {
+    /// Returns the service name in this `NAPTRResult`.  Any invalid UTF-8 is
+    /// replaced by U+FFFD.
+    pub fn service_name(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.service_name_bytes())
+    }
+
+    /// Returns the service name in this `NAPTRResult`, exactly as received.
+    pub fn service_name_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr(
                 (*self.naptr_reply).service as *const libc::c_char);
-            str::from_utf8(c_str.to_bytes()).unwrap()
+            c_str.to_bytes()
         }
     }
 
-    /// Returns the regular expression in this `NAPTRResult`.
-    pub fn reg_exp(&self) -> &str {
+    /// Returns the regular expression in this `NAPTRResult`.  Any invalid UTF-8
+    /// is replaced by U+FFFD.
+    pub fn reg_exp(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.reg_exp_bytes())
+    }
+
+    /// Returns the regular expression in this `NAPTRResult`, exactly as
+    /// received.
+    pub fn reg_exp_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr(
                 (*self.naptr_reply).regexp as *const libc::c_char);
-            str::from_utf8(c_str.to_bytes()).unwrap()
+            c_str.to_bytes()
         }
     }
 
-    /// Returns the replacement pattern in this `NAPTRResult`.
-    pub fn replacement_pattern(&self) -> &str {
+    /// Returns the replacement pattern in this `NAPTRResult`.  Any invalid
+    /// UTF-8 is replaced by U+FFFD.
+    pub fn replacement_pattern(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.replacement_pattern_bytes())
+    }
+
+    /// Returns the replacement pattern in this `NAPTRResult`, exactly as
+    /// received.
+    pub fn replacement_pattern_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr((*self.naptr_reply).replacement);
-            str::from_utf8(c_str.to_bytes()).unwrap()
+            c_str.to_bytes()
         }
     }
 
@@ -157,6 +225,23 @@ impl<'a> NAPTRResult<'a> {
     pub fn preference(&self) -> u16 {
         unsafe { (*self.naptr_reply).preference }
     }
+
+    /// Returns an owned copy of this `NAPTRResult`.
+    pub fn to_owned(&self) -> OwnedNAPTRResult {
+        OwnedNAPTRResult {
+            flags: self.flags().into_owned(),
+            flags_bytes: self.flags_bytes().to_vec(),
+            service_name: self.service_name().into_owned(),
+            service_name_bytes: self.service_name_bytes().to_vec(),
+            reg_exp: self.reg_exp().into_owned(),
+            reg_exp_bytes: self.reg_exp_bytes().to_vec(),
+            replacement_pattern: self.replacement_pattern().into_owned(),
+            replacement_pattern_bytes:
+                self.replacement_pattern_bytes().to_vec(),
+            order: self.order(),
+            preference: self.preference(),
+        }
+    }
 }
 
 pub unsafe extern "C" fn query_naptr_callback<F>(
@@ -166,12 +251,14 @@ pub unsafe extern "C" fn query_naptr_callback<F>(
     abuf: *mut libc::c_uchar,
     alen: libc::c_int)
     where F: FnOnce(Result<NAPTRResults, AresError>) + 'static {
-    let handler: Box<F> = mem::transmute(arg);
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let data = slice::from_raw_parts(abuf, alen as usize);
-        NAPTRResults::parse_from(data)
-    };
-    handler(result);
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            NAPTRResults::parse_from(data)
+        };
+        handler(result);
+    });
 }
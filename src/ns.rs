@@ -1,15 +1,16 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::borrow::Cow;
 use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::mem;
 use std::ptr;
 use std::slice;
-use std::str;
 
 use error::AresError;
 use types::hostent;
+use unwind;
 use utils::ares_error;
 
 /// The result of a successful NS lookup.
@@ -24,6 +25,17 @@ pub struct NSResult<'a> {
     phantom: PhantomData<&'a hostent>,
 }
 
+/// An owned copy of a single NS record, as returned by
+/// `NSResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedNSResult {
+    /// The name server.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub name_server: String,
+
+    /// The name server, exactly as received.
+    pub name_server_bytes: Vec<u8>,
+}
+
 impl NSResults {
     /// Obtain an `NSResults` from the response to an NS lookup.
     pub fn parse_from(data: &[u8]) -> Result<NSResults, AresError> {
@@ -57,6 +69,12 @@ impl NSResults {
             phantom: PhantomData,
         }
     }
+
+    /// Returns owned copies of the `NSResult` values in this `NSResults`,
+    /// which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedNSResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
 }
 
 pub struct NSResultsIterator<'a> {
@@ -107,11 +125,25 @@ unsafe impl<'a> Send for NSResultsIterator<'a> { }
 unsafe impl<'a> Sync for NSResultsIterator<'a> { }
 
 impl<'a> NSResult<'a> {
-    /// Returns the name server in this `NSResult`.
-    pub fn name_server(&self) -> &str {
+    /// Returns the name server in this `NSResult`.  Any invalid UTF-8 is
+    /// replaced by U+FFFD.
+    pub fn name_server(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.name_server_bytes())
+    }
+
+    /// Returns the name server in this `NSResult`, exactly as received.
+    pub fn name_server_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr(self.h_alias);
-            str::from_utf8_unchecked(c_str.to_bytes())
+            c_str.to_bytes()
+        }
+    }
+
+    /// Returns an owned copy of this `NSResult`.
+    pub fn to_owned(&self) -> OwnedNSResult {
+        OwnedNSResult {
+            name_server: self.name_server().into_owned(),
+            name_server_bytes: self.name_server_bytes().to_vec(),
         }
     }
 }
@@ -123,12 +155,14 @@ pub unsafe extern "C" fn query_ns_callback<F>(
     abuf: *mut libc::c_uchar,
     alen: libc::c_int)
     where F: FnOnce(Result<NSResults, AresError>) + 'static {
-    let handler: Box<F> = mem::transmute(arg);
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let data = slice::from_raw_parts(abuf, alen as usize);
-        NSResults::parse_from(data)
-    };
-    handler(result);
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            NSResults::parse_from(data)
+        };
+        handler(result);
+    });
 }
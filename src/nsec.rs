@@ -0,0 +1,198 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful NSEC lookup.
+pub struct NSECResults {
+    nsec_records: Vec<NSECRecord>,
+}
+
+/// The contents of a single NSEC record.
+pub struct NSECResult<'a> {
+    // A single result - reference into an `NSECResults`.
+    nsec_record: &'a NSECRecord,
+}
+
+/// An owned copy of a single NSEC record, as returned by
+/// `NSECResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedNSECResult {
+    /// The next name in the zone.
+    pub next_domain_name: String,
+
+    /// The types of record that exist at the owner name.
+    pub types: Vec<u16>,
+}
+
+// An NSEC record, as parsed from the response.
+struct NSECRecord {
+    next_domain_name: String,
+    types: Vec<u16>,
+}
+
+impl NSECResults {
+    /// Obtain an `NSECResults` from the response to an NSEC lookup.
+    pub fn parse_from(data: &[u8]) -> Result<NSECResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut nsec_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::NSEC)? {
+            // The next domain name must not be compressed.
+            let mut reader = Reader::new(rr.rdata());
+            let nsec_record = NSECRecord {
+                next_domain_name: reader.read_uncompressed_name()?,
+                types: reader.read_type_bitmap()?,
+            };
+            nsec_records.push(nsec_record);
+        }
+        let results = NSECResults {
+            nsec_records: nsec_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `NSECResult` values in this
+    /// `NSECResults`.
+    pub fn iter(&self) -> NSECResultsIterator {
+        NSECResultsIterator {
+            next: self.nsec_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `NSECResult` values in this
+    /// `NSECResults`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedNSECResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct NSECResultsIterator<'a> {
+    next: slice::Iter<'a, NSECRecord>,
+}
+
+impl<'a> Iterator for NSECResultsIterator<'a> {
+    type Item = NSECResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|nsec_record| {
+            NSECResult {
+                nsec_record: nsec_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a NSECResults {
+    type Item = NSECResult<'a>;
+    type IntoIter = NSECResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> NSECResult<'a> {
+    /// Returns the next name in the zone, from this `NSECResult`.
+    pub fn next_domain_name(&self) -> &str {
+        &self.nsec_record.next_domain_name
+    }
+
+    /// Returns the types of record that exist at the owner name of this
+    /// `NSECResult`, in increasing order.
+    pub fn types(&self) -> &[u16] {
+        &self.nsec_record.types
+    }
+
+    /// Returns an owned copy of this `NSECResult`.
+    pub fn to_owned(&self) -> OwnedNSECResult {
+        OwnedNSECResult {
+            next_domain_name: self.next_domain_name().to_owned(),
+            types: self.types().to_vec(),
+        }
+    }
+}
+
+pub unsafe extern "C" fn query_nsec_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<NSECResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            NSECResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_rejects_overlong_rdata,
+        assert_rejects_rdata,
+        parse_test_response,
+    };
+    use types::QueryType;
+    use super::{
+        OwnedNSECResult,
+        NSECResults,
+    };
+
+    #[test]
+    fn parses_records() {
+        let rdata = b"\x04host\x07example\x03com\x00\x00\x01\x40";
+        let results = parse_test_response(
+            NSECResults::parse_from,
+            QueryType::NSEC,
+            &[rdata]).unwrap();
+        let expected = vec![
+            OwnedNSECResult {
+                next_domain_name: "host.example.com".to_owned(),
+                types: vec![1],
+            },
+        ];
+        assert_eq!(results.to_owned(), expected);
+        let result = results.iter().next().unwrap();
+        assert_eq!(result.next_domain_name(), "host.example.com");
+        assert_eq!(result.types(), &[1]);
+    }
+
+    #[test]
+    fn rejects_compressed_next_domain_name() {
+        assert_rejects_rdata(NSECResults::parse_from, QueryType::NSEC, &[
+            b"\xc0\x0c\x00\x01\x40",
+            b"\x04host\xc0\x0c\x00\x01\x40"]);
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(NSECResults::parse_from, QueryType::NSEC, &[
+            b"",
+            b"\x04host",
+            b"\x00\x00\x02\x40"]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        let rdata: &[u8] = b"\x00\x00\x01\x40";
+        assert_rejects_overlong_rdata(
+            NSECResults::parse_from,
+            QueryType::NSEC,
+            rdata);
+    }
+}
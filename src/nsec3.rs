@@ -0,0 +1,252 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful NSEC3 lookup.
+pub struct NSEC3Results {
+    nsec3_records: Vec<NSEC3Record>,
+}
+
+/// The contents of a single NSEC3 record.
+pub struct NSEC3Result<'a> {
+    // A single result - reference into an `NSEC3Results`.
+    nsec3_record: &'a NSEC3Record,
+}
+
+/// An owned copy of a single NSEC3 record, as returned by
+/// `NSEC3Result::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedNSEC3Result {
+    /// The hash algorithm.
+    pub hash_algorithm: u8,
+
+    /// The flags.
+    pub flags: u8,
+
+    /// The number of additional hash iterations.
+    pub iterations: u16,
+
+    /// The salt.
+    pub salt: Vec<u8>,
+
+    /// The next hashed owner name.
+    pub next_hashed_owner_name: Vec<u8>,
+
+    /// The types of record that exist at the original owner name.
+    pub types: Vec<u16>,
+}
+
+// An NSEC3 record, as parsed from the response.
+struct NSEC3Record {
+    hash_algorithm: u8,
+    flags: u8,
+    iterations: u16,
+    salt: Vec<u8>,
+    next_hashed_owner_name: Vec<u8>,
+    types: Vec<u16>,
+}
+
+impl NSEC3Results {
+    /// Obtain an `NSEC3Results` from the response to an NSEC3 lookup.
+    pub fn parse_from(data: &[u8]) -> Result<NSEC3Results, AresError> {
+        let message = Message::parse(data)?;
+        let mut nsec3_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::NSEC3)? {
+            let mut reader = Reader::new(rr.rdata());
+            let hash_algorithm = reader.read_u8()?;
+            let flags = reader.read_u8()?;
+            let iterations = reader.read_u16()?;
+            let salt = reader.read_string()?.to_vec();
+            let next_hashed_owner_name = reader.read_string()?.to_vec();
+            let nsec3_record = NSEC3Record {
+                hash_algorithm: hash_algorithm,
+                flags: flags,
+                iterations: iterations,
+                salt: salt,
+                next_hashed_owner_name: next_hashed_owner_name,
+                types: reader.read_type_bitmap()?,
+            };
+            nsec3_records.push(nsec3_record);
+        }
+        let results = NSEC3Results {
+            nsec3_records: nsec3_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `NSEC3Result` values in this
+    /// `NSEC3Results`.
+    pub fn iter(&self) -> NSEC3ResultsIterator {
+        NSEC3ResultsIterator {
+            next: self.nsec3_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `NSEC3Result` values in this
+    /// `NSEC3Results`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedNSEC3Result> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct NSEC3ResultsIterator<'a> {
+    next: slice::Iter<'a, NSEC3Record>,
+}
+
+impl<'a> Iterator for NSEC3ResultsIterator<'a> {
+    type Item = NSEC3Result<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|nsec3_record| {
+            NSEC3Result {
+                nsec3_record: nsec3_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a NSEC3Results {
+    type Item = NSEC3Result<'a>;
+    type IntoIter = NSEC3ResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> NSEC3Result<'a> {
+    /// Returns the hash algorithm used by this `NSEC3Result`.
+    pub fn hash_algorithm(&self) -> u8 {
+        self.nsec3_record.hash_algorithm
+    }
+
+    /// Returns the flags from this `NSEC3Result`.
+    pub fn flags(&self) -> u8 {
+        self.nsec3_record.flags
+    }
+
+    /// Returns the number of additional hash iterations used by this
+    /// `NSEC3Result`.
+    pub fn iterations(&self) -> u16 {
+        self.nsec3_record.iterations
+    }
+
+    /// Returns the salt used by this `NSEC3Result`.
+    pub fn salt(&self) -> &[u8] {
+        &self.nsec3_record.salt
+    }
+
+    /// Returns the next hashed owner name in the zone, from this
+    /// `NSEC3Result`.  This is the raw hash, not its base32 encoding.
+    pub fn next_hashed_owner_name(&self) -> &[u8] {
+        &self.nsec3_record.next_hashed_owner_name
+    }
+
+    /// Returns the types of record that exist at the original owner name of
+    /// this `NSEC3Result`, in increasing order.
+    pub fn types(&self) -> &[u16] {
+        &self.nsec3_record.types
+    }
+
+    /// Returns whether the opt-out flag is set in this `NSEC3Result`.
+    pub fn opt_out(&self) -> bool {
+        self.flags() & 0x01 != 0
+    }
+
+    /// Returns an owned copy of this `NSEC3Result`.
+    pub fn to_owned(&self) -> OwnedNSEC3Result {
+        OwnedNSEC3Result {
+            hash_algorithm: self.hash_algorithm(),
+            flags: self.flags(),
+            iterations: self.iterations(),
+            salt: self.salt().to_vec(),
+            next_hashed_owner_name: self.next_hashed_owner_name().to_vec(),
+            types: self.types().to_vec(),
+        }
+    }
+}
+
+pub unsafe extern "C" fn query_nsec3_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<NSEC3Results, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            NSEC3Results::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_rejects_overlong_rdata,
+        assert_rejects_rdata,
+        parse_test_response,
+    };
+    use types::QueryType;
+    use super::{
+        OwnedNSEC3Result,
+        NSEC3Results,
+    };
+
+    #[test]
+    fn parses_records() {
+        let rdata = b"\x01\x01\x00\x0a\x02\xaa\xbb\x02\x12\x34\x00\x01\x40";
+        let results = parse_test_response(
+            NSEC3Results::parse_from,
+            QueryType::NSEC3,
+            &[rdata]).unwrap();
+        let expected = vec![
+            OwnedNSEC3Result {
+                hash_algorithm: 1,
+                flags: 1,
+                iterations: 10,
+                salt: vec![0xaa, 0xbb],
+                next_hashed_owner_name: vec![0x12, 0x34],
+                types: vec![1],
+            },
+        ];
+        assert_eq!(results.to_owned(), expected);
+        let result = results.iter().next().unwrap();
+        assert!(result.opt_out());
+        assert_eq!(result.salt(), &[0xaa, 0xbb]);
+        assert_eq!(result.next_hashed_owner_name(), &[0x12, 0x34]);
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(NSEC3Results::parse_from, QueryType::NSEC3, &[
+            b"",
+            b"\x01\x00\x00\x0a",
+            b"\x01\x00\x00\x0a\x02\xaa",
+            b"\x01\x00\x00\x0a\x00\x01\x12\x00"]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        let rdata: &[u8] = b"\x01\x00\x00\x0a\x00\x01\x12";
+        assert_rejects_overlong_rdata(
+            NSEC3Results::parse_from,
+            QueryType::NSEC3,
+            rdata);
+    }
+}
@@ -0,0 +1,68 @@
+extern crate libc;
+
+use std::os::unix::io;
+use std::time::Duration;
+
+// Waits until at least one of the file descriptors is ready, or until the
+// timeout expires.  `fds` and the return value are both lists of
+// `(fd, readable, writable)`; the return value lists only those file
+// descriptors on which something happened.
+//
+// Errors and hang-ups are reported as 'readable', so that c-ares gets a
+// chance to notice them.
+pub fn poll_fds(
+    fds: &[(io::RawFd, bool, bool)],
+    timeout: Option<Duration>) -> Vec<(io::RawFd, bool, bool)> {
+    let mut pollfds: Vec<libc::pollfd> = fds
+        .iter()
+        .map(|&(fd, readable, writable)| {
+            let mut events = 0;
+            if readable { events = events | libc::POLLIN; }
+            if writable { events = events | libc::POLLOUT; }
+            libc::pollfd {
+                fd: fd as libc::c_int,
+                events: events,
+                revents: 0,
+            }
+        })
+        .collect();
+    let results = unsafe {
+        libc::poll(
+            pollfds.as_mut_ptr(),
+            pollfds.len() as libc::nfds_t,
+            timeout_ms(timeout))
+    };
+    if results <= 0 {
+        return Vec::new()
+    }
+    pollfds
+        .iter()
+        .filter(|p| p.revents != 0)
+        .map(|p| {
+            let readable_events =
+                libc::POLLIN | libc::POLLERR | libc::POLLHUP;
+            let readable = (p.revents & readable_events) != 0;
+            let writable = (p.revents & libc::POLLOUT) != 0;
+            (p.fd as io::RawFd, readable, writable)
+        })
+        .collect()
+}
+
+// Converts a timeout to milliseconds, as expected by poll().  Rounds up, so
+// that we don't wake up just before a deadline and find nothing to do.
+fn timeout_ms(timeout: Option<Duration>) -> libc::c_int {
+    match timeout {
+        None => -1,
+        Some(duration) => {
+            let millis = duration.as_secs()
+                .saturating_mul(1000)
+                .saturating_add(
+                    (duration.subsec_nanos() as u64 + 999_999) / 1_000_000);
+            if millis > i32::MAX as u64 {
+                i32::MAX
+            } else {
+                millis as libc::c_int
+            }
+        }
+    }
+}
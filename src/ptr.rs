@@ -1,15 +1,16 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::borrow::Cow;
 use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
 use std::mem;
 use std::ptr;
 use std::slice;
-use std::str;
 
 use error::AresError;
 use types::hostent;
+use unwind;
 use utils::ares_error;
 
 /// The result of a successful PTR lookup.
@@ -24,6 +25,17 @@ pub struct PTRResult<'a> {
     phantom: PhantomData<&'a hostent>,
 }
 
+/// An owned copy of a single PTR record, as returned by
+/// `PTRResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedPTRResult {
+    /// The canonical name.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub cname: String,
+
+    /// The canonical name, exactly as received.
+    pub cname_bytes: Vec<u8>,
+}
+
 impl PTRResults {
     /// Obtain a `PTRResults` from the response to a PTR lookup.
     pub fn parse_from(data: &[u8]) -> Result<PTRResults, AresError> {
@@ -62,6 +74,12 @@ impl PTRResults {
             phantom: PhantomData,
         }
     }
+
+    /// Returns owned copies of the `PTRResult` values in this `PTRResults`,
+    /// which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedPTRResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
 }
 
 pub struct PTRResultsIterator<'a> {
@@ -112,11 +130,25 @@ unsafe impl<'a> Send for PTRResultsIterator<'a> { }
 unsafe impl<'a> Sync for PTRResultsIterator<'a> { }
 
 impl<'a> PTRResult<'a> {
-    /// Returns the canonical name in this `PTRResult`.
-    pub fn cname(&self) -> &str {
+    /// Returns the canonical name in this `PTRResult`.  Any invalid UTF-8 is
+    /// replaced by U+FFFD.
+    pub fn cname(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.cname_bytes())
+    }
+
+    /// Returns the canonical name in this `PTRResult`, exactly as received.
+    pub fn cname_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr(self.h_alias);
-            str::from_utf8_unchecked(c_str.to_bytes())
+            c_str.to_bytes()
+        }
+    }
+
+    /// Returns an owned copy of this `PTRResult`.
+    pub fn to_owned(&self) -> OwnedPTRResult {
+        OwnedPTRResult {
+            cname: self.cname().into_owned(),
+            cname_bytes: self.cname_bytes().to_vec(),
         }
     }
 }
@@ -128,12 +160,14 @@ pub unsafe extern "C" fn query_ptr_callback<F>(
     abuf: *mut libc::c_uchar,
     alen: libc::c_int)
     where F: FnOnce(Result<PTRResults, AresError>) + 'static {
-    let handler: Box<F> = mem::transmute(arg);
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let data = slice::from_raw_parts(abuf, alen as usize);
-        PTRResults::parse_from(data)
-    };
-    handler(result);
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            PTRResults::parse_from(data)
+        };
+        handler(result);
+    });
 }
@@ -0,0 +1,114 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::ptr;
+use std::slice;
+
+use error::AresError;
+use unwind;
+use utils::{
+    ares_error,
+    to_c_name,
+};
+
+/// Build a DNS query message, suitable for sending with `send_raw()`.
+///
+/// The query asks for the records of type `query_type` and class `dns_class`
+/// associated with `name`, and has the given `id`.  As with `query_raw()`,
+/// the type and class are plain numbers.  If `recursion_desired` is set then
+/// the query asks the server to resolve it recursively.  If `max_udp_size` is
+/// provided, an EDNS OPT record advertising that UDP payload size is
+/// included.
+#[cfg(not(feature = "old-cares"))]
+pub fn create_query(
+    name: &str,
+    dns_class: u16,
+    query_type: u16,
+    id: u16,
+    recursion_desired: bool,
+    max_udp_size: Option<u16>) -> Result<Vec<u8>, AresError> {
+    let c_name = to_c_name(name)?;
+    let mut buf: *mut libc::c_uchar = ptr::null_mut();
+    let mut buflen: libc::c_int = 0;
+    let status = unsafe {
+        c_ares_sys::ares_create_query(
+            c_name.as_ptr(),
+            dns_class as libc::c_int,
+            query_type as libc::c_int,
+            id,
+            recursion_desired as libc::c_int,
+            &mut buf,
+            &mut buflen,
+            max_udp_size.unwrap_or(0) as libc::c_int)
+    };
+    take_query_buffer(status, buf, buflen)
+}
+
+/// Build a DNS query message, suitable for sending with `send_raw()`.
+///
+/// Older versions of `c-ares` can't add EDNS records to a query, so this
+/// fails with `ENOTIMP` if `max_udp_size` is provided.
+#[cfg(feature = "old-cares")]
+pub fn create_query(
+    name: &str,
+    dns_class: u16,
+    query_type: u16,
+    id: u16,
+    recursion_desired: bool,
+    max_udp_size: Option<u16>) -> Result<Vec<u8>, AresError> {
+    if max_udp_size.is_some() {
+        return Err(AresError::ENOTIMP)
+    }
+    let c_name = to_c_name(name)?;
+    let mut buf: *mut libc::c_uchar = ptr::null_mut();
+    let mut buflen: libc::c_int = 0;
+    let status = unsafe {
+        c_ares_sys::ares_mkquery(
+            c_name.as_ptr(),
+            dns_class as libc::c_int,
+            query_type as libc::c_int,
+            id,
+            recursion_desired as libc::c_int,
+            &mut buf,
+            &mut buflen)
+    };
+    take_query_buffer(status, buf, buflen)
+}
+
+// Copy a query built by c-ares into a `Vec`, and free the original.
+fn take_query_buffer(
+    status: libc::c_int,
+    buf: *mut libc::c_uchar,
+    buflen: libc::c_int) -> Result<Vec<u8>, AresError> {
+    if status != c_ares_sys::ARES_SUCCESS {
+        Err(ares_error(status))
+    } else {
+        let query = unsafe {
+            let data = slice::from_raw_parts(buf, buflen as usize);
+            let query = data.to_vec();
+            c_ares_sys::ares_free_string(buf as *mut libc::c_void);
+            query
+        };
+        Ok(query)
+    }
+}
+
+pub unsafe extern "C" fn query_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<&[u8], AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            Ok(data)
+        };
+        handler(result);
+    });
+}
@@ -0,0 +1,199 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+
+use error::AresError;
+use message::Message;
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful RP lookup.
+pub struct RPResults {
+    rp_records: Vec<RPRecord>,
+}
+
+/// The contents of a single RP record.
+pub struct RPResult<'a> {
+    // A single result - reference into an `RPResults`.
+    rp_record: &'a RPRecord,
+}
+
+/// An owned copy of a single RP record, as returned by
+/// `RPResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedRPResult {
+    /// The mailbox of the responsible person.
+    pub mailbox: String,
+
+    /// The name at which TXT records with more information can be found.
+    pub txt_domain: String,
+}
+
+// An RP record, as parsed from the response.
+struct RPRecord {
+    mailbox: String,
+    txt_domain: String,
+}
+
+impl RPResults {
+    /// Obtain an `RPResults` from the response to an RP lookup.
+    pub fn parse_from(data: &[u8]) -> Result<RPResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut rp_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::RP)? {
+            // The names in an RP record may be compressed.
+            let mut reader = message.rdata_reader(rr);
+            let rp_record = RPRecord {
+                mailbox: reader.read_name()?,
+                txt_domain: reader.read_name()?,
+            };
+            if !reader.is_empty() {
+                return Err(AresError::EBADRESP)
+            }
+            rp_records.push(rp_record);
+        }
+        let results = RPResults {
+            rp_records: rp_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `RPResult` values in this
+    /// `RPResults`.
+    pub fn iter(&self) -> RPResultsIterator {
+        RPResultsIterator {
+            next: self.rp_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `RPResult` values in this
+    /// `RPResults`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedRPResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct RPResultsIterator<'a> {
+    next: slice::Iter<'a, RPRecord>,
+}
+
+impl<'a> Iterator for RPResultsIterator<'a> {
+    type Item = RPResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|rp_record| {
+            RPResult {
+                rp_record: rp_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a RPResults {
+    type Item = RPResult<'a>;
+    type IntoIter = RPResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> RPResult<'a> {
+    /// Returns the mailbox of the responsible person from this `RPResult`,
+    /// encoded as a domain name: the first label is the local part.
+    pub fn mailbox(&self) -> &str {
+        &self.rp_record.mailbox
+    }
+
+    /// Returns the name at which TXT records with more information can be
+    /// found, from this `RPResult`.  This is empty if there are none.
+    pub fn txt_domain(&self) -> &str {
+        &self.rp_record.txt_domain
+    }
+
+    /// Returns an owned copy of this `RPResult`.
+    pub fn to_owned(&self) -> OwnedRPResult {
+        OwnedRPResult {
+            mailbox: self.mailbox().to_owned(),
+            txt_domain: self.txt_domain().to_owned(),
+        }
+    }
+}
+
+pub unsafe extern "C" fn query_rp_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<RPResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            RPResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_rejects_rdata,
+        parse_test_response,
+    };
+    use types::QueryType;
+    use super::{
+        OwnedRPResult,
+        RPResults,
+    };
+
+    #[test]
+    fn parses_records() {
+        let results = parse_test_response(
+            RPResults::parse_from,
+            QueryType::RP,
+            &[b"\x05admin\x03org\x00\x04info\x03org\x00"])
+            .unwrap();
+        let result = results.iter().next().unwrap();
+        assert_eq!(result.mailbox(), "admin.org");
+        assert_eq!(result.txt_domain(), "info.org");
+    }
+
+    #[test]
+    fn follows_compressed_names() {
+        // Offset 12 is the name in the question: example.com.
+        let results = parse_test_response(
+            RPResults::parse_from,
+            QueryType::RP,
+            &[b"\x05admin\xc0\x0c\x00"]).unwrap();
+        let expected = vec![
+            OwnedRPResult {
+                mailbox: "admin.example.com".to_owned(),
+                txt_domain: "".to_owned(),
+            },
+        ];
+        assert_eq!(results.to_owned(), expected);
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(RPResults::parse_from, QueryType::RP, &[
+            b"",
+            b"\x05admin\x00",
+            b"\x05admin\x00\x04inf"]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        assert_rejects_rdata(
+            RPResults::parse_from,
+            QueryType::RP,
+            &[b"\x00\x00\x00"]);
+    }
+}
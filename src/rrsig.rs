@@ -0,0 +1,298 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful RRSIG lookup.
+pub struct RRSIGResults {
+    rrsig_records: Vec<RRSIGRecord>,
+}
+
+/// The contents of a single RRSIG record.
+pub struct RRSIGResult<'a> {
+    // A single result - reference into an `RRSIGResults`.
+    rrsig_record: &'a RRSIGRecord,
+}
+
+/// An owned copy of a single RRSIG record, as returned by
+/// `RRSIGResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedRRSIGResult {
+    /// The type of the records signed.
+    pub type_covered: u16,
+
+    /// The algorithm.
+    pub algorithm: u8,
+
+    /// The number of labels in the original owner name.
+    pub labels: u8,
+
+    /// The original time-to-live.
+    pub original_ttl: u32,
+
+    /// The expiration time.
+    pub expiration: u32,
+
+    /// The inception time.
+    pub inception: u32,
+
+    /// The key tag.
+    pub key_tag: u16,
+
+    /// The signer's name.
+    pub signer_name: String,
+
+    /// The signature.
+    pub signature: Vec<u8>,
+}
+
+// An RRSIG record, as parsed from the response.
+struct RRSIGRecord {
+    type_covered: u16,
+    algorithm: u8,
+    labels: u8,
+    original_ttl: u32,
+    expiration: u32,
+    inception: u32,
+    key_tag: u16,
+    signer_name: String,
+    signature: Vec<u8>,
+}
+
+impl RRSIGResults {
+    /// Obtain an `RRSIGResults` from the response to an RRSIG lookup.
+    pub fn parse_from(data: &[u8]) -> Result<RRSIGResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut rrsig_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::RRSIG)? {
+            // The signer's name must not be compressed.
+            let mut reader = Reader::new(rr.rdata());
+            let rrsig_record = RRSIGRecord {
+                type_covered: reader.read_u16()?,
+                algorithm: reader.read_u8()?,
+                labels: reader.read_u8()?,
+                original_ttl: reader.read_u32()?,
+                expiration: reader.read_u32()?,
+                inception: reader.read_u32()?,
+                key_tag: reader.read_u16()?,
+                signer_name: reader.read_uncompressed_name()?,
+                signature: reader.read_rest().to_vec(),
+            };
+            rrsig_records.push(rrsig_record);
+        }
+        let results = RRSIGResults {
+            rrsig_records: rrsig_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `RRSIGResult` values in this
+    /// `RRSIGResults`.
+    pub fn iter(&self) -> RRSIGResultsIterator {
+        RRSIGResultsIterator {
+            next: self.rrsig_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `RRSIGResult` values in this
+    /// `RRSIGResults`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedRRSIGResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct RRSIGResultsIterator<'a> {
+    next: slice::Iter<'a, RRSIGRecord>,
+}
+
+impl<'a> Iterator for RRSIGResultsIterator<'a> {
+    type Item = RRSIGResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|rrsig_record| {
+            RRSIGResult {
+                rrsig_record: rrsig_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a RRSIGResults {
+    type Item = RRSIGResult<'a>;
+    type IntoIter = RRSIGResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> RRSIGResult<'a> {
+    /// Returns the type of the records that this `RRSIGResult` signs.
+    pub fn type_covered(&self) -> u16 {
+        self.rrsig_record.type_covered
+    }
+
+    /// Returns the algorithm used to make this `RRSIGResult`.
+    pub fn algorithm(&self) -> u8 {
+        self.rrsig_record.algorithm
+    }
+
+    /// Returns the number of labels in the original owner name of the records
+    /// signed by this `RRSIGResult`.
+    pub fn labels(&self) -> u8 {
+        self.rrsig_record.labels
+    }
+
+    /// Returns the original time-to-live of the records signed by this
+    /// `RRSIGResult`.
+    pub fn original_ttl(&self) -> u32 {
+        self.rrsig_record.original_ttl
+    }
+
+    /// Returns the time at which this `RRSIGResult` expires, in seconds since
+    /// the epoch (modulo 2^32).
+    pub fn expiration(&self) -> u32 {
+        self.rrsig_record.expiration
+    }
+
+    /// Returns the time from which this `RRSIGResult` is valid, in seconds
+    /// since the epoch (modulo 2^32).
+    pub fn inception(&self) -> u32 {
+        self.rrsig_record.inception
+    }
+
+    /// Returns the key tag of the DNSKEY that made this `RRSIGResult`.
+    pub fn key_tag(&self) -> u16 {
+        self.rrsig_record.key_tag
+    }
+
+    /// Returns the name of the zone that made this `RRSIGResult`.
+    pub fn signer_name(&self) -> &str {
+        &self.rrsig_record.signer_name
+    }
+
+    /// Returns the signature from this `RRSIGResult`.
+    pub fn signature(&self) -> &[u8] {
+        &self.rrsig_record.signature
+    }
+
+    /// Returns an owned copy of this `RRSIGResult`.
+    pub fn to_owned(&self) -> OwnedRRSIGResult {
+        OwnedRRSIGResult {
+            type_covered: self.type_covered(),
+            algorithm: self.algorithm(),
+            labels: self.labels(),
+            original_ttl: self.original_ttl(),
+            expiration: self.expiration(),
+            inception: self.inception(),
+            key_tag: self.key_tag(),
+            signer_name: self.signer_name().to_owned(),
+            signature: self.signature().to_vec(),
+        }
+    }
+}
+
+pub unsafe extern "C" fn query_rrsig_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<RRSIGResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            RRSIGResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_rejects_overlong_rdata,
+        assert_rejects_rdata,
+        parse_test_response,
+    };
+    use types::QueryType;
+    use super::{
+        OwnedRRSIGResult,
+        RRSIGResults,
+    };
+
+    // An RRSIG covering A records, with the given signer name - which is
+    // already in wire format.
+    fn rrsig(signer_name: &[u8]) -> Vec<u8> {
+        let mut rdata = vec![0, 1, 8, 2];
+        rdata.extend_from_slice(&[0, 0, 0x0e, 0x10]);
+        rdata.extend_from_slice(&[0x5f, 0x5e, 0x10, 0x00]);
+        rdata.extend_from_slice(&[0x5f, 0x36, 0x83, 0x00]);
+        rdata.extend_from_slice(&[0x0a, 0x52]);
+        rdata.extend_from_slice(signer_name);
+        rdata.extend_from_slice(&[0xab, 0xcd]);
+        rdata
+    }
+
+    #[test]
+    fn parses_records() {
+        let rdata = rrsig(b"\x07example\x03com\x00");
+        let results = parse_test_response(
+            RRSIGResults::parse_from,
+            QueryType::RRSIG,
+            &[&rdata]).unwrap();
+        let expected = vec![
+            OwnedRRSIGResult {
+                type_covered: 1,
+                algorithm: 8,
+                labels: 2,
+                original_ttl: 3600,
+                expiration: 0x5f5e1000,
+                inception: 0x5f368300,
+                key_tag: 2642,
+                signer_name: "example.com".to_owned(),
+                signature: vec![0xab, 0xcd],
+            },
+        ];
+        assert_eq!(results.to_owned(), expected);
+        let result = results.iter().next().unwrap();
+        assert_eq!(result.signer_name(), "example.com");
+        assert_eq!(result.signature(), &[0xab, 0xcd]);
+    }
+
+    #[test]
+    fn rejects_compressed_signer_name() {
+        assert_rejects_rdata(RRSIGResults::parse_from, QueryType::RRSIG, &[
+            &rrsig(b"\xc0\x0c"),
+            &rrsig(b"\x03www\xc0\x0c")]);
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        let rdata = rrsig(b"\x07example\x03com\x00");
+        assert_rejects_rdata(RRSIGResults::parse_from, QueryType::RRSIG, &[
+            &rdata[..17],
+            &rdata[..22]]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        let rdata: &[u8] = &rrsig(b"\x00");
+        assert_rejects_overlong_rdata(
+            RRSIGResults::parse_from,
+            QueryType::RRSIG,
+            rdata);
+    }
+}
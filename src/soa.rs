@@ -1,14 +1,15 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::borrow::Cow;
 use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::mem;
 use std::ptr;
 use std::slice;
-use std::str;
 
 use error::AresError ;
+use unwind;
 use utils::ares_error;
 
 /// The result of a successful SOA lookup.
@@ -17,6 +18,38 @@ pub struct SOAResult {
     phantom: PhantomData<c_ares_sys::Struct_ares_soa_reply>,
 }
 
+/// An owned copy of an `SOAResult`, as returned by
+/// `SOAResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedSOAResult {
+    /// The name server.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub name_server: String,
+
+    /// The name server, exactly as received.
+    pub name_server_bytes: Vec<u8>,
+
+    /// The hostmaster.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub hostmaster: String,
+
+    /// The hostmaster, exactly as received.
+    pub hostmaster_bytes: Vec<u8>,
+
+    /// The serial number.
+    pub serial: u32,
+
+    /// The refresh time.
+    pub refresh: u32,
+
+    /// The retry time.
+    pub retry: u32,
+
+    /// The expire time.
+    pub expire: u32,
+
+    /// The minimum time-to-live.
+    pub min_ttl: u32,
+}
+
 impl SOAResult {
     #[cfg(feature = "old-cares")]
     pub fn parse_from(_data: &[u8]) -> Result<SOAResult, AresError> {
@@ -49,19 +82,31 @@ impl SOAResult {
         }
     }
 
-    /// Returns the name server from this `SOAResult`.
-    pub fn name_server(&self) -> &str {
+    /// Returns the name server from this `SOAResult`.  Any invalid UTF-8 is
+    /// replaced by U+FFFD.
+    pub fn name_server(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.name_server_bytes())
+    }
+
+    /// Returns the name server from this `SOAResult`, exactly as received.
+    pub fn name_server_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr((*self.soa_reply).nsname);
-            str::from_utf8_unchecked(c_str.to_bytes())
+            c_str.to_bytes()
         }
     }
 
-    /// Returns the hostmaster from this `SOAResult`.
-    pub fn hostmaster(&self) -> &str {
+    /// Returns the hostmaster from this `SOAResult`.  Any invalid UTF-8 is
+    /// replaced by U+FFFD.
+    pub fn hostmaster(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.hostmaster_bytes())
+    }
+
+    /// Returns the hostmaster from this `SOAResult`, exactly as received.
+    pub fn hostmaster_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr((*self.soa_reply).hostmaster);
-            str::from_utf8_unchecked(c_str.to_bytes())
+            c_str.to_bytes()
         }
     }
 
@@ -89,6 +134,21 @@ impl SOAResult {
     pub fn min_ttl(&self) -> u32 {
         unsafe { (*self.soa_reply).minttl }
     }
+
+    /// Returns an owned copy of this `SOAResult`, which can outlive it.
+    pub fn to_owned(&self) -> OwnedSOAResult {
+        OwnedSOAResult {
+            name_server: self.name_server().into_owned(),
+            name_server_bytes: self.name_server_bytes().to_vec(),
+            hostmaster: self.hostmaster().into_owned(),
+            hostmaster_bytes: self.hostmaster_bytes().to_vec(),
+            serial: self.serial(),
+            refresh: self.refresh(),
+            retry: self.retry(),
+            expire: self.expire(),
+            min_ttl: self.min_ttl(),
+        }
+    }
 }
 
 impl Drop for SOAResult {
@@ -109,12 +169,14 @@ pub unsafe extern "C" fn query_soa_callback<F>(
     abuf: *mut libc::c_uchar,
     alen: libc::c_int)
     where F: FnOnce(Result<SOAResult, AresError>) + 'static {
-    let handler: Box<F> = mem::transmute(arg);
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let data = slice::from_raw_parts(abuf, alen as usize);
-        SOAResult::parse_from(data)
-    };
-    handler(result);
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            SOAResult::parse_from(data)
+        };
+        handler(result);
+    });
 }
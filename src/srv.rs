@@ -1,14 +1,15 @@
 extern crate c_ares_sys;
 extern crate libc;
 
+use std::borrow::Cow;
 use std::ffi::CStr;
 use std::marker::PhantomData;
 use std::mem;
-use std::str;
 use std::ptr;
 use std::slice;
 
 use error::AresError;
+use unwind;
 use utils::ares_error;
 
 /// The result of a successful SRV lookup.
@@ -24,6 +25,26 @@ pub struct SRVResult<'a> {
     phantom: PhantomData<&'a c_ares_sys::Struct_ares_srv_reply>,
 }
 
+/// An owned copy of a single SRV record, as returned by
+/// `SRVResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedSRVResult {
+    /// The hostname.  Any invalid UTF-8 is replaced by U+FFFD.
+    pub host: String,
+
+    /// The hostname, exactly as received.
+    pub host_bytes: Vec<u8>,
+
+    /// The weight.
+    pub weight: u16,
+
+    /// The priority.
+    pub priority: u16,
+
+    /// The port.
+    pub port: u16,
+}
+
 impl SRVResults {
     /// Obtain an `SRVResults` from the response to an SRV lookup.
     pub fn parse_from(data: &[u8]) -> Result<SRVResults, AresError> {
@@ -57,6 +78,12 @@ impl SRVResults {
             phantom: PhantomData,
         }
     }
+
+    /// Returns owned copies of the `SRVResult` values in this `SRVResults`,
+    /// which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedSRVResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
 }
 
 pub struct SRVResultsIterator<'a> {
@@ -106,11 +133,17 @@ unsafe impl<'a> Send for SRVResultsIterator<'a> { }
 unsafe impl<'a> Sync for SRVResultsIterator<'a> { }
 
 impl<'a> SRVResult<'a> {
-    /// Returns the hostname in this `SRVResult`.
-    pub fn host(&self) -> &str {
+    /// Returns the hostname in this `SRVResult`.  Any invalid UTF-8 is replaced
+    /// by U+FFFD.
+    pub fn host(&self) -> Cow<str> {
+        String::from_utf8_lossy(self.host_bytes())
+    }
+
+    /// Returns the hostname in this `SRVResult`, exactly as received.
+    pub fn host_bytes(&self) -> &[u8] {
         unsafe {
             let c_str = CStr::from_ptr((*self.srv_reply).host);
-            str::from_utf8(c_str.to_bytes()).unwrap()
+            c_str.to_bytes()
         }
     }
 
@@ -128,6 +161,17 @@ impl<'a> SRVResult<'a> {
     pub fn port(&self) -> u16 {
         unsafe { (*self.srv_reply).port }
     }
+
+    /// Returns an owned copy of this `SRVResult`.
+    pub fn to_owned(&self) -> OwnedSRVResult {
+        OwnedSRVResult {
+            host: self.host().into_owned(),
+            host_bytes: self.host_bytes().to_vec(),
+            weight: self.weight(),
+            priority: self.priority(),
+            port: self.port(),
+        }
+    }
 }
 
 pub unsafe extern "C" fn query_srv_callback<F>(
@@ -137,12 +181,14 @@ pub unsafe extern "C" fn query_srv_callback<F>(
     abuf: *mut libc::c_uchar,
     alen: libc::c_int)
     where F: FnOnce(Result<SRVResults, AresError>) + 'static {
-    let handler: Box<F> = mem::transmute(arg);
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let data = slice::from_raw_parts(abuf, alen as usize);
-        SRVResults::parse_from(data)
-    };
-    handler(result);
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            SRVResults::parse_from(data)
+        };
+        handler(result);
+    });
 }
@@ -0,0 +1,211 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful SSHFP lookup.
+pub struct SSHFPResults {
+    sshfp_records: Vec<SSHFPRecord>,
+}
+
+/// The contents of a single SSHFP record.
+pub struct SSHFPResult<'a> {
+    // A single result - reference into an `SSHFPResults`.
+    sshfp_record: &'a SSHFPRecord,
+}
+
+/// An owned copy of a single SSHFP record, as returned by
+/// `SSHFPResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedSSHFPResult {
+    /// The public key algorithm.
+    pub algorithm: u8,
+
+    /// The fingerprint type.
+    pub fingerprint_type: u8,
+
+    /// The fingerprint.
+    pub fingerprint: Vec<u8>,
+}
+
+// An SSHFP record, as parsed from the response.
+struct SSHFPRecord {
+    algorithm: u8,
+    fingerprint_type: u8,
+    fingerprint: Vec<u8>,
+}
+
+impl SSHFPResults {
+    /// Obtain an `SSHFPResults` from the response to an SSHFP lookup.
+    pub fn parse_from(data: &[u8]) -> Result<SSHFPResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut sshfp_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::SSHFP)? {
+            let mut reader = Reader::new(rr.rdata());
+            let sshfp_record = SSHFPRecord {
+                algorithm: reader.read_u8()?,
+                fingerprint_type: reader.read_u8()?,
+                fingerprint: reader.read_rest().to_vec(),
+            };
+            sshfp_records.push(sshfp_record);
+        }
+        let results = SSHFPResults {
+            sshfp_records: sshfp_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `SSHFPResult` values in this
+    /// `SSHFPResults`.
+    pub fn iter(&self) -> SSHFPResultsIterator {
+        SSHFPResultsIterator {
+            next: self.sshfp_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `SSHFPResult` values in this
+    /// `SSHFPResults`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedSSHFPResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct SSHFPResultsIterator<'a> {
+    next: slice::Iter<'a, SSHFPRecord>,
+}
+
+impl<'a> Iterator for SSHFPResultsIterator<'a> {
+    type Item = SSHFPResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|sshfp_record| {
+            SSHFPResult {
+                sshfp_record: sshfp_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a SSHFPResults {
+    type Item = SSHFPResult<'a>;
+    type IntoIter = SSHFPResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> SSHFPResult<'a> {
+    /// Returns the algorithm of the key that this `SSHFPResult` describes:
+    /// for instance 1 for RSA, or 4 for Ed25519.
+    pub fn algorithm(&self) -> u8 {
+        self.sshfp_record.algorithm
+    }
+
+    /// Returns the type of fingerprint in this `SSHFPResult`: 1 for SHA-1,
+    /// or 2 for SHA-256.
+    pub fn fingerprint_type(&self) -> u8 {
+        self.sshfp_record.fingerprint_type
+    }
+
+    /// Returns the fingerprint from this `SSHFPResult`.
+    pub fn fingerprint(&self) -> &[u8] {
+        &self.sshfp_record.fingerprint
+    }
+
+    /// Returns an owned copy of this `SSHFPResult`.
+    pub fn to_owned(&self) -> OwnedSSHFPResult {
+        OwnedSSHFPResult {
+            algorithm: self.algorithm(),
+            fingerprint_type: self.fingerprint_type(),
+            fingerprint: self.fingerprint().to_vec(),
+        }
+    }
+}
+
+pub unsafe extern "C" fn query_sshfp_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<SSHFPResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            SSHFPResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_rejects_overlong_rdata,
+        assert_rejects_rdata,
+        parse_test_response,
+    };
+    use types::QueryType;
+    use super::{
+        OwnedSSHFPResult,
+        SSHFPResults,
+    };
+
+    #[test]
+    fn parses_records() {
+        let results = parse_test_response(
+            SSHFPResults::parse_from,
+            QueryType::SSHFP,
+            &[&[4, 2, 0x12, 0x34]]).unwrap();
+        let result = results.iter().next().unwrap();
+        assert_eq!(result.algorithm(), 4);
+        assert_eq!(result.fingerprint_type(), 2);
+        assert_eq!(result.fingerprint(), &[0x12, 0x34]);
+
+        let results = parse_test_response(
+            SSHFPResults::parse_from,
+            QueryType::SSHFP,
+            &[&[1, 1], &[3, 2, 0xff]]).unwrap();
+        let expected = vec![
+            OwnedSSHFPResult {
+                algorithm: 1,
+                fingerprint_type: 1,
+                fingerprint: Vec::new(),
+            },
+            OwnedSSHFPResult {
+                algorithm: 3,
+                fingerprint_type: 2,
+                fingerprint: vec![0xff],
+            },
+        ];
+        assert_eq!(results.to_owned(), expected);
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(SSHFPResults::parse_from, QueryType::SSHFP, &[
+            &[],
+            &[4]]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        assert_rejects_overlong_rdata(
+            SSHFPResults::parse_from,
+            QueryType::SSHFP,
+            &[4, 2, 0x12]);
+    }
+}
@@ -0,0 +1,465 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::net::{
+    Ipv4Addr,
+    Ipv6Addr,
+};
+use std::slice;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful SVCB or HTTPS lookup.  HTTPS records have the
+/// same format as SVCB records, so are represented in the same way.
+pub struct SVCBResults {
+    svcb_records: Vec<SVCBRecord>,
+}
+
+/// The contents of a single SVCB or HTTPS record.
+pub struct SVCBResult<'a> {
+    // A single result - reference into an `SVCBResults`.
+    svcb_record: &'a SVCBRecord,
+}
+
+/// An owned copy of a single SVCB or HTTPS record, as returned by
+/// `SVCBResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedSVCBResult {
+    /// The priority.
+    pub priority: u16,
+
+    /// The target name.
+    pub target: String,
+
+    /// The service parameters.
+    pub params: Vec<SvcParam>,
+}
+
+// An SVCB or HTTPS record, as parsed from the response.
+struct SVCBRecord {
+    priority: u16,
+    target: String,
+    params: Vec<SvcParam>,
+}
+
+impl SVCBResults {
+    /// Obtain an `SVCBResults` from the response to an SVCB lookup.
+    pub fn parse_from(data: &[u8]) -> Result<SVCBResults, AresError> {
+        SVCBResults::parse_records(data, QueryType::SVCB)
+    }
+
+    /// Obtain an `SVCBResults` from the response to an HTTPS lookup.
+    pub fn parse_https_from(data: &[u8]) -> Result<SVCBResults, AresError> {
+        SVCBResults::parse_records(data, QueryType::HTTPS)
+    }
+
+    fn parse_records(
+        data: &[u8],
+        rr_type: QueryType) -> Result<SVCBResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut svcb_records = Vec::new();
+        for rr in message.answers_of_type(rr_type)? {
+            // The target name must not be compressed.
+            let mut reader = Reader::new(rr.rdata());
+            let svcb_record = SVCBRecord {
+                priority: reader.read_u16()?,
+                target: reader.read_uncompressed_name()?,
+                params: read_params(&mut reader)?,
+            };
+            svcb_records.push(svcb_record);
+        }
+        let results = SVCBResults {
+            svcb_records: svcb_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `SVCBResult` values in this
+    /// `SVCBResults`.
+    pub fn iter(&self) -> SVCBResultsIterator {
+        SVCBResultsIterator {
+            next: self.svcb_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `SVCBResult` values in this
+    /// `SVCBResults`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedSVCBResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct SVCBResultsIterator<'a> {
+    next: slice::Iter<'a, SVCBRecord>,
+}
+
+impl<'a> Iterator for SVCBResultsIterator<'a> {
+    type Item = SVCBResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|svcb_record| {
+            SVCBResult {
+                svcb_record: svcb_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a SVCBResults {
+    type Item = SVCBResult<'a>;
+    type IntoIter = SVCBResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> SVCBResult<'a> {
+    /// Returns the priority from this `SVCBResult`.  Zero indicates that
+    /// the record is in alias mode; otherwise it is in service mode, and lower
+    /// values are preferred.
+    pub fn priority(&self) -> u16 {
+        self.svcb_record.priority
+    }
+
+    /// Returns the target name from this `SVCBResult`.  An empty target
+    /// means the owner name of the record - in service mode - or that the
+    /// service is unavailable - in alias mode.
+    pub fn target(&self) -> &str {
+        &self.svcb_record.target
+    }
+
+    /// Returns the service parameters from this `SVCBResult`, in order of
+    /// their keys.
+    pub fn params(&self) -> &[SvcParam] {
+        &self.svcb_record.params
+    }
+
+    /// Returns whether this `SVCBResult` is in alias mode.
+    pub fn is_alias(&self) -> bool {
+        self.priority() == 0
+    }
+
+    /// Returns an owned copy of this `SVCBResult`.
+    pub fn to_owned(&self) -> OwnedSVCBResult {
+        OwnedSVCBResult {
+            priority: self.priority(),
+            target: self.target().to_owned(),
+            params: self.params().to_vec(),
+        }
+    }
+}
+
+/// A service parameter from an SVCB or HTTPS record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SvcParam {
+    /// Keys of parameters that a client must understand to use the record.
+    Mandatory(Vec<u16>),
+
+    /// Application protocol identifiers - for instance "h2" or "h3".
+    Alpn(Vec<Vec<u8>>),
+
+    /// The default protocol is not supported.
+    NoDefaultAlpn,
+
+    /// The port to connect to.
+    Port(u16),
+
+    /// IPv4 addresses that the client may use to reach the service.
+    Ipv4Hint(Vec<Ipv4Addr>),
+
+    /// An Encrypted ClientHello configuration list.
+    Ech(Vec<u8>),
+
+    /// IPv6 addresses that the client may use to reach the service.
+    Ipv6Hint(Vec<Ipv6Addr>),
+
+    /// A parameter that isn't understood, as its key and raw value.
+    Unknown(u16, Vec<u8>),
+}
+
+// Read the service parameters that make up the rest of the record.  Keys
+// must appear in strictly increasing order.
+fn read_params(reader: &mut Reader) -> Result<Vec<SvcParam>, AresError> {
+    let mut params = Vec::new();
+    let mut last_key = None;
+    while !reader.is_empty() {
+        let key = reader.read_u16()?;
+        if last_key.map_or(false, |last| key <= last) {
+            return Err(AresError::EBADRESP)
+        }
+        last_key = Some(key);
+        let length = reader.read_u16()?;
+        let value = reader.read_bytes(length as usize)?;
+        params.push(parse_param(key, value)?);
+    }
+    Ok(params)
+}
+
+fn parse_param(key: u16, value: &[u8]) -> Result<SvcParam, AresError> {
+    let mut reader = Reader::new(value);
+    let param = match key {
+        0 => {
+            let mut keys = Vec::new();
+            while !reader.is_empty() {
+                keys.push(reader.read_u16()?);
+            }
+            SvcParam::Mandatory(keys)
+        },
+        1 => {
+            let mut protocols = Vec::new();
+            while !reader.is_empty() {
+                let protocol = reader.read_string()?;
+                if protocol.is_empty() {
+                    return Err(AresError::EBADRESP)
+                }
+                protocols.push(protocol.to_vec());
+            }
+            SvcParam::Alpn(protocols)
+        },
+        2 => SvcParam::NoDefaultAlpn,
+        3 => SvcParam::Port(reader.read_u16()?),
+        4 => {
+            let mut addresses = Vec::new();
+            while !reader.is_empty() {
+                let octets = reader.read_bytes(4)?;
+                let address = Ipv4Addr::new(
+                    octets[0],
+                    octets[1],
+                    octets[2],
+                    octets[3]);
+                addresses.push(address);
+            }
+            SvcParam::Ipv4Hint(addresses)
+        },
+        5 => SvcParam::Ech(reader.read_rest().to_vec()),
+        6 => {
+            let mut addresses = Vec::new();
+            while !reader.is_empty() {
+                let mut segments = [0; 8];
+                for segment in &mut segments {
+                    *segment = reader.read_u16()?;
+                }
+                let address = Ipv6Addr::new(
+                    segments[0],
+                    segments[1],
+                    segments[2],
+                    segments[3],
+                    segments[4],
+                    segments[5],
+                    segments[6],
+                    segments[7]);
+                addresses.push(address);
+            }
+            SvcParam::Ipv6Hint(addresses)
+        },
+        _ => SvcParam::Unknown(key, reader.read_rest().to_vec()),
+    };
+    if !reader.is_empty() {
+        return Err(AresError::EBADRESP)
+    }
+    Ok(param)
+}
+
+pub unsafe extern "C" fn query_svcb_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<SVCBResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            SVCBResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+pub unsafe extern "C" fn query_https_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<SVCBResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            SVCBResults::parse_https_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use error::AresError;
+    use message::{
+        assert_bad_response,
+        assert_rejects_overlong_rdata,
+        assert_rejects_rdata,
+        parse_test_response,
+        test_response,
+    };
+    use types::QueryType;
+    use super::{
+        SVCBResults,
+        SvcParam,
+    };
+
+    fn assert_no_data(result: Result<SVCBResults, AresError>) {
+        match result {
+            Err(AresError::ENODATA) => (),
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("unexpected success"),
+        }
+    }
+
+    #[test]
+    fn parses_alias_mode() {
+        let results = parse_test_response(
+            SVCBResults::parse_from,
+            QueryType::SVCB,
+            &[b"\x00\x00\x03svc\x07example\x00"]).unwrap();
+        let result = results.iter().next().unwrap();
+        assert!(result.is_alias());
+        assert_eq!(result.target(), "svc.example");
+        assert!(result.params().is_empty());
+    }
+
+    #[test]
+    fn parses_params() {
+        let mut rdata = b"\x00\x01\x00".to_vec();
+        rdata.extend_from_slice(b"\x00\x00\x00\x02\x00\x01");
+        rdata.extend_from_slice(b"\x00\x01\x00\x06\x02h2\x02h3");
+        rdata.extend_from_slice(b"\x00\x02\x00\x00");
+        rdata.extend_from_slice(b"\x00\x03\x00\x02\x01\xbb");
+        rdata.extend_from_slice(b"\x00\x04\x00\x08\xc0\x00\x02\x01");
+        rdata.extend_from_slice(b"\xc0\x00\x02\x02");
+        rdata.extend_from_slice(b"\x00\x05\x00\x02\xab\xcd");
+        rdata.extend_from_slice(b"\x00\x06\x00\x10\x20\x01\x0d\xb8");
+        rdata.extend_from_slice(&[0; 11]);
+        rdata.push(1);
+        rdata.extend_from_slice(b"\x01\x00\x00\x01\xff");
+        let results = parse_test_response(
+            SVCBResults::parse_from,
+            QueryType::SVCB,
+            &[&rdata]).unwrap();
+        let result = results.iter().next().unwrap();
+        assert!(!result.is_alias());
+        assert_eq!(result.priority(), 1);
+        assert_eq!(result.target(), "");
+        let expected = vec![
+            SvcParam::Mandatory(vec![1]),
+            SvcParam::Alpn(vec![b"h2".to_vec(), b"h3".to_vec()]),
+            SvcParam::NoDefaultAlpn,
+            SvcParam::Port(443),
+            SvcParam::Ipv4Hint(vec![
+                Ipv4Addr::new(192, 0, 2, 1),
+                Ipv4Addr::new(192, 0, 2, 2)]),
+            SvcParam::Ech(vec![0xab, 0xcd]),
+            SvcParam::Ipv6Hint(vec!["2001:db8::1".parse().unwrap()]),
+            SvcParam::Unknown(256, vec![0xff]),
+        ];
+        assert_eq!(result.params(), &expected[..]);
+    }
+
+    #[test]
+    fn accepts_only_the_queried_type() {
+        let rdata: &[u8] = b"\x00\x01\x00";
+        let svcb = test_response(QueryType::SVCB, &[rdata]);
+        let https = test_response(QueryType::HTTPS, &[rdata]);
+        assert_eq!(SVCBResults::parse_from(&svcb).unwrap().iter().count(), 1);
+        assert_no_data(SVCBResults::parse_from(&https));
+        let results = SVCBResults::parse_https_from(&https).unwrap();
+        assert_eq!(results.iter().count(), 1);
+        assert_no_data(SVCBResults::parse_https_from(&svcb));
+    }
+
+    #[test]
+    fn rejects_compressed_target() {
+        assert_rejects_rdata(SVCBResults::parse_from, QueryType::SVCB, &[
+            b"\x00\x01\xc0\x0c",
+            b"\x00\x01\x03svc\xc0\x0c"]);
+    }
+
+    #[test]
+    fn rejects_out_of_order_keys() {
+        let mut rdata = b"\x00\x01\x00".to_vec();
+        rdata.extend_from_slice(b"\x00\x03\x00\x02\x01\xbb");
+        rdata.extend_from_slice(b"\x00\x01\x00\x03\x02h2");
+        assert_rejects_rdata(
+            SVCBResults::parse_from,
+            QueryType::SVCB,
+            &[&rdata]);
+
+        let mut rdata = b"\x00\x01\x00".to_vec();
+        rdata.extend_from_slice(b"\x00\x03\x00\x02\x01\xbb");
+        rdata.extend_from_slice(b"\x00\x03\x00\x02\x01\xbb");
+        assert_rejects_rdata(
+            SVCBResults::parse_from,
+            QueryType::SVCB,
+            &[&rdata]);
+    }
+
+    // A service mode record with the given parameters, already in wire
+    // format.
+    fn with_params(params: &[u8]) -> Vec<u8> {
+        let mut rdata = b"\x00\x01\x00".to_vec();
+        rdata.extend_from_slice(params);
+        rdata
+    }
+
+    #[test]
+    fn rejects_bad_param_values() {
+        // A port that is too long, an empty protocol, a partial address, and
+        // a value for no-default-alpn.
+        let params: [&[u8]; 4] = [
+            b"\x00\x03\x00\x03\x01\xbb\x00",
+            b"\x00\x01\x00\x01\x00",
+            b"\x00\x04\x00\x03\xc0\x00\x02",
+            b"\x00\x02\x00\x01\x00",
+        ];
+        for param in &params {
+            assert_bad_response(parse_test_response(
+                SVCBResults::parse_from,
+                QueryType::SVCB,
+                &[&with_params(param)]));
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(SVCBResults::parse_from, QueryType::SVCB, &[
+            b"",
+            b"\x00\x01",
+            b"\x00\x01\x03svc",
+            b"\x00\x01\x00\x00\x03\x00\x02\x01",
+            b"\x00\x01\x00\x00\x03"]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        assert_rejects_overlong_rdata(
+            SVCBResults::parse_from,
+            QueryType::SVCB,
+            b"\x00\x01\x00");
+    }
+}
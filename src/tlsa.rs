@@ -0,0 +1,227 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful TLSA lookup.
+pub struct TLSAResults {
+    tlsa_records: Vec<TLSARecord>,
+}
+
+/// The contents of a single TLSA record.
+pub struct TLSAResult<'a> {
+    // A single result - reference into a `TLSAResults`.
+    tlsa_record: &'a TLSARecord,
+}
+
+/// An owned copy of a single TLSA record, as returned by
+/// `TLSAResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedTLSAResult {
+    /// The certificate usage.
+    pub usage: u8,
+
+    /// The selector.
+    pub selector: u8,
+
+    /// The matching type.
+    pub matching_type: u8,
+
+    /// The certificate association data.
+    pub data: Vec<u8>,
+}
+
+// A TLSA record, as parsed from the response.
+struct TLSARecord {
+    usage: u8,
+    selector: u8,
+    matching_type: u8,
+    data: Vec<u8>,
+}
+
+impl TLSAResults {
+    /// Obtain a `TLSAResults` from the response to a TLSA lookup.
+    pub fn parse_from(data: &[u8]) -> Result<TLSAResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut tlsa_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::TLSA)? {
+            let mut reader = Reader::new(rr.rdata());
+            let tlsa_record = TLSARecord {
+                usage: reader.read_u8()?,
+                selector: reader.read_u8()?,
+                matching_type: reader.read_u8()?,
+                data: reader.read_rest().to_vec(),
+            };
+            tlsa_records.push(tlsa_record);
+        }
+        let results = TLSAResults {
+            tlsa_records: tlsa_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `TLSAResult` values in this
+    /// `TLSAResults`.
+    pub fn iter(&self) -> TLSAResultsIterator {
+        TLSAResultsIterator {
+            next: self.tlsa_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `TLSAResult` values in this
+    /// `TLSAResults`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedTLSAResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct TLSAResultsIterator<'a> {
+    next: slice::Iter<'a, TLSARecord>,
+}
+
+impl<'a> Iterator for TLSAResultsIterator<'a> {
+    type Item = TLSAResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|tlsa_record| {
+            TLSAResult {
+                tlsa_record: tlsa_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a TLSAResults {
+    type Item = TLSAResult<'a>;
+    type IntoIter = TLSAResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> TLSAResult<'a> {
+    /// Returns the certificate usage from this `TLSAResult`: how the
+    /// certificate association is to be used when verifying the server's
+    /// certificate.
+    pub fn usage(&self) -> u8 {
+        self.tlsa_record.usage
+    }
+
+    /// Returns the selector from this `TLSAResult`: which part of the
+    /// server's certificate is matched against the association data.
+    pub fn selector(&self) -> u8 {
+        self.tlsa_record.selector
+    }
+
+    /// Returns the matching type from this `TLSAResult`: how the
+    /// association data is presented - in full, or as a hash.
+    pub fn matching_type(&self) -> u8 {
+        self.tlsa_record.matching_type
+    }
+
+    /// Returns the certificate association data from this `TLSAResult`.
+    pub fn data(&self) -> &[u8] {
+        &self.tlsa_record.data
+    }
+
+    /// Returns an owned copy of this `TLSAResult`.
+    pub fn to_owned(&self) -> OwnedTLSAResult {
+        OwnedTLSAResult {
+            usage: self.usage(),
+            selector: self.selector(),
+            matching_type: self.matching_type(),
+            data: self.data().to_vec(),
+        }
+    }
+}
+
+pub unsafe extern "C" fn query_tlsa_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<TLSAResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            TLSAResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_rejects_overlong_rdata,
+        assert_rejects_rdata,
+        parse_test_response,
+    };
+    use types::QueryType;
+    use super::{
+        OwnedTLSAResult,
+        TLSAResults,
+    };
+
+    #[test]
+    fn parses_records() {
+        let results = parse_test_response(
+            TLSAResults::parse_from,
+            QueryType::TLSA,
+            &[&[3, 1, 1, 0xde, 0xad, 0xbe, 0xef]]).unwrap();
+        let result = results.iter().next().unwrap();
+        assert_eq!(result.usage(), 3);
+        assert_eq!(result.selector(), 1);
+        assert_eq!(result.matching_type(), 1);
+        assert_eq!(result.data(), &[0xde, 0xad, 0xbe, 0xef]);
+
+        let results = parse_test_response(
+            TLSAResults::parse_from,
+            QueryType::TLSA,
+            &[&[2, 0, 0], &[3, 1, 2, 1]]).unwrap();
+        let expected = vec![
+            OwnedTLSAResult {
+                usage: 2,
+                selector: 0,
+                matching_type: 0,
+                data: Vec::new(),
+            },
+            OwnedTLSAResult {
+                usage: 3,
+                selector: 1,
+                matching_type: 2,
+                data: vec![1],
+            },
+        ];
+        assert_eq!(results.to_owned(), expected);
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(TLSAResults::parse_from, QueryType::TLSA, &[
+            &[],
+            &[3, 1]]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        assert_rejects_overlong_rdata(
+            TLSAResults::parse_from,
+            QueryType::TLSA,
+            &[3, 1, 1, 0xff]);
+    }
+}
@@ -0,0 +1,771 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::os::unix::io;
+use std::panic::{
+    self,
+    AssertUnwindSafe,
+};
+use std::pin::Pin;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::task::{
+    Context,
+    Poll,
+};
+use std::time::Duration;
+
+use handle::QueryHandle;
+use tokio::io::Interest;
+use tokio::io::unix::AsyncFd;
+use tokio::sync::{
+    mpsc,
+    oneshot,
+};
+use tokio::time::{
+    Instant,
+    Sleep,
+};
+
+use a::AResults;
+use aaaa::AAAAResults;
+use caa::CAAResults;
+use channel::{
+    Channel,
+    Options,
+};
+use cname::CNameResult;
+use dnskey::DNSKEYResults;
+use ds::DSResults;
+use error::AresError;
+use futureresolver::CAresFuture;
+use hinfo::HINFOResults;
+use host::OwnedHostResults;
+use loc::LOCResults;
+use mx::MXResults;
+use nameinfo::OwnedNameInfoResult;
+use naptr::NAPTRResults;
+use ni_flags::NIFlags;
+use ns::NSResults;
+use nsec::NSECResults;
+use nsec3::NSEC3Results;
+use poll::poll_fds;
+use ptr::PTRResults;
+use rp::RPResults;
+use rrsig::RRSIGResults;
+use srv::SRVResults;
+use sshfp::SSHFPResults;
+use svcb::SVCBResults;
+use tlsa::TLSAResults;
+use types::{
+    AddressFamily,
+    INVALID_FD,
+    IpAddr,
+};
+use uri::URIResults;
+use txt::TXTResults;
+use soa::SOAResult;
+
+// The most times that the driver will go round its loop before yielding to
+// other tasks.
+const MAX_PASSES: usize = 16;
+
+/// A resolver that is driven by the Tokio runtime.
+///
+/// A `TokioResolver` owns a `Channel`, and spawns a task that registers the
+/// channel's sockets with Tokio - calling `process_fd()` whenever they become
+/// ready, and whenever `c-ares` has timeouts to handle.  Each query returns a
+/// `CAresFuture`, which completes when the query does.
+///
+/// Dropping the `TokioResolver` stops the task.  Any queries still outstanding
+/// complete with `Err(AresError::EDESTRUCTION)`.
+///
+/// This is available only if the `tokio` feature is enabled.
+pub struct TokioResolver {
+    channel: Arc<Mutex<Channel>>,
+    events: mpsc::UnboundedSender<DriverEvent>,
+    query_timeout: Mutex<Option<Duration>>,
+
+    // Dropping this tells the driver task to stop.
+    #[allow(dead_code)]
+    shutdown: oneshot::Sender<()>,
+}
+
+impl TokioResolver {
+    /// Create a new `TokioResolver`, using the given `Options`.
+    ///
+    /// The resolver needs to know when `c-ares` changes its interest in
+    /// sockets, so this replaces any socket state callback that has been set
+    /// on `options`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from outside of a Tokio runtime.
+    pub fn new(mut options: Options) -> Result<TokioResolver, AresError> {
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        let socket_events = events_tx.clone();
+        options.set_socket_state_callback(move |fd, readable, writable| {
+            let event = DriverEvent::SocketState(fd, readable, writable);
+            let _ = socket_events.send(event);
+        });
+        let channel = Arc::new(Mutex::new(Channel::new(options)?));
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let driver = Driver {
+            channel: channel.clone(),
+            events: events_rx,
+            shutdown: shutdown_rx,
+            sockets: HashMap::new(),
+            sleep: Box::pin(tokio::time::sleep(Duration::from_secs(0))),
+        };
+        tokio::spawn(driver);
+        let resolver = TokioResolver {
+            channel: channel,
+            events: events_tx,
+            query_timeout: Mutex::new(None),
+            shutdown: shutdown_tx,
+        };
+        Ok(resolver)
+    }
+
+    /// Set the list of servers to contact, instead of the servers specified
+    /// in resolv.conf or the local named.
+    ///
+    /// String format is `host[:port]`.  IPv6 addresses with ports require
+    /// square brackets eg `[2001:4860:4860::8888]:53`.
+    pub fn set_servers(&self, servers: &[&str]) -> Result<&Self, AresError> {
+        self.channel.lock().unwrap().set_servers(servers).map(|_| self)
+    }
+
+    /// Give each query made from now on a deadline, `timeout` after it's
+    /// made.  Queries that take longer fail with `ETIMEOUT`.  See
+    /// `Channel::set_query_deadline()`.
+    pub fn set_query_timeout(&self, timeout: Duration) -> &Self {
+        *self.query_timeout.lock().unwrap() = Some(timeout);
+        self
+    }
+
+    /// Look up the A records associated with `name`.
+    pub fn query_a(
+        &self,
+        name: &str) -> CAresFuture<Result<AResults, AresError>> {
+        self.submit(|channel, done| channel.query_a(name, done))
+    }
+
+    /// Look up the AAAA records associated with `name`.
+    pub fn query_aaaa(
+        &self,
+        name: &str) -> CAresFuture<Result<AAAAResults, AresError>> {
+        self.submit(|channel, done| channel.query_aaaa(name, done))
+    }
+
+    /// Look up the CNAME record associated with `name`.
+    pub fn query_cname(
+        &self,
+        name: &str) -> CAresFuture<Result<CNameResult, AresError>> {
+        self.submit(|channel, done| channel.query_cname(name, done))
+    }
+
+    /// Look up the MX records associated with `name`.
+    pub fn query_mx(
+        &self,
+        name: &str) -> CAresFuture<Result<MXResults, AresError>> {
+        self.submit(|channel, done| channel.query_mx(name, done))
+    }
+
+    /// Look up the NAPTR records associated with `name`.
+    pub fn query_naptr(
+        &self,
+        name: &str) -> CAresFuture<Result<NAPTRResults, AresError>> {
+        self.submit(|channel, done| channel.query_naptr(name, done))
+    }
+
+    /// Look up the NS records associated with `name`.
+    pub fn query_ns(
+        &self,
+        name: &str) -> CAresFuture<Result<NSResults, AresError>> {
+        self.submit(|channel, done| channel.query_ns(name, done))
+    }
+
+    /// Look up the PTR records associated with `name`.
+    pub fn query_ptr(
+        &self,
+        name: &str) -> CAresFuture<Result<PTRResults, AresError>> {
+        self.submit(|channel, done| channel.query_ptr(name, done))
+    }
+
+    /// Look up the SRV records associated with `name`.
+    pub fn query_srv(
+        &self,
+        name: &str) -> CAresFuture<Result<SRVResults, AresError>> {
+        self.submit(|channel, done| channel.query_srv(name, done))
+    }
+
+    /// Look up the TXT records associated with `name`.
+    pub fn query_txt(
+        &self,
+        name: &str) -> CAresFuture<Result<TXTResults, AresError>> {
+        self.submit(|channel, done| channel.query_txt(name, done))
+    }
+
+    /// Look up the SOA record associated with `name`.
+    pub fn query_soa(
+        &self,
+        name: &str) -> CAresFuture<Result<SOAResult, AresError>> {
+        self.submit(|channel, done| channel.query_soa(name, done))
+    }
+
+    /// Look up the CAA records associated with `name`.
+    pub fn query_caa(
+        &self,
+        name: &str) -> CAresFuture<Result<CAAResults, AresError>> {
+        self.submit(|channel, done| channel.query_caa(name, done))
+    }
+
+    /// Look up the TLSA records associated with `name`.
+    pub fn query_tlsa(
+        &self,
+        name: &str) -> CAresFuture<Result<TLSAResults, AresError>> {
+        self.submit(|channel, done| channel.query_tlsa(name, done))
+    }
+
+    /// Look up the SSHFP records associated with `name`.
+    pub fn query_sshfp(
+        &self,
+        name: &str) -> CAresFuture<Result<SSHFPResults, AresError>> {
+        self.submit(|channel, done| channel.query_sshfp(name, done))
+    }
+
+    /// Look up the SVCB records associated with `name`.
+    pub fn query_svcb(
+        &self,
+        name: &str) -> CAresFuture<Result<SVCBResults, AresError>> {
+        self.submit(|channel, done| channel.query_svcb(name, done))
+    }
+
+    /// Look up the HTTPS records associated with `name`.
+    pub fn query_https(
+        &self,
+        name: &str) -> CAresFuture<Result<SVCBResults, AresError>> {
+        self.submit(|channel, done| channel.query_https(name, done))
+    }
+
+    /// Look up the DNSKEY records associated with `name`.
+    pub fn query_dnskey(
+        &self,
+        name: &str) -> CAresFuture<Result<DNSKEYResults, AresError>> {
+        self.submit(|channel, done| channel.query_dnskey(name, done))
+    }
+
+    /// Look up the DS records associated with `name`.
+    pub fn query_ds(
+        &self,
+        name: &str) -> CAresFuture<Result<DSResults, AresError>> {
+        self.submit(|channel, done| channel.query_ds(name, done))
+    }
+
+    /// Look up the RRSIG records associated with `name`.
+    pub fn query_rrsig(
+        &self,
+        name: &str) -> CAresFuture<Result<RRSIGResults, AresError>> {
+        self.submit(|channel, done| channel.query_rrsig(name, done))
+    }
+
+    /// Look up the NSEC records associated with `name`.
+    pub fn query_nsec(
+        &self,
+        name: &str) -> CAresFuture<Result<NSECResults, AresError>> {
+        self.submit(|channel, done| channel.query_nsec(name, done))
+    }
+
+    /// Look up the NSEC3 records associated with `name`.
+    pub fn query_nsec3(
+        &self,
+        name: &str) -> CAresFuture<Result<NSEC3Results, AresError>> {
+        self.submit(|channel, done| channel.query_nsec3(name, done))
+    }
+
+    /// Look up the URI records associated with `name`.
+    pub fn query_uri(
+        &self,
+        name: &str) -> CAresFuture<Result<URIResults, AresError>> {
+        self.submit(|channel, done| channel.query_uri(name, done))
+    }
+
+    /// Look up the HINFO records associated with `name`.
+    pub fn query_hinfo(
+        &self,
+        name: &str) -> CAresFuture<Result<HINFOResults, AresError>> {
+        self.submit(|channel, done| channel.query_hinfo(name, done))
+    }
+
+    /// Look up the LOC records associated with `name`.
+    pub fn query_loc(
+        &self,
+        name: &str) -> CAresFuture<Result<LOCResults, AresError>> {
+        self.submit(|channel, done| channel.query_loc(name, done))
+    }
+
+    /// Look up the RP records associated with `name`.
+    pub fn query_rp(
+        &self,
+        name: &str) -> CAresFuture<Result<RPResults, AresError>> {
+        self.submit(|channel, done| channel.query_rp(name, done))
+    }
+
+    /// Search for the A records associated with `name`.
+    ///
+    /// Like `query_a()`, but applies the configured search domains.
+    pub fn search_a(
+        &self,
+        name: &str) -> CAresFuture<Result<AResults, AresError>> {
+        self.submit(|channel, done| channel.search_a(name, done))
+    }
+
+    /// Search for the AAAA records associated with `name`.
+    ///
+    /// Like `query_aaaa()`, but applies the configured search domains.
+    pub fn search_aaaa(
+        &self,
+        name: &str) -> CAresFuture<Result<AAAAResults, AresError>> {
+        self.submit(|channel, done| channel.search_aaaa(name, done))
+    }
+
+    /// Search for the CNAME record associated with `name`.
+    ///
+    /// Like `query_cname()`, but applies the configured search domains.
+    pub fn search_cname(
+        &self,
+        name: &str) -> CAresFuture<Result<CNameResult, AresError>> {
+        self.submit(|channel, done| channel.search_cname(name, done))
+    }
+
+    /// Search for the MX records associated with `name`.
+    ///
+    /// Like `query_mx()`, but applies the configured search domains.
+    pub fn search_mx(
+        &self,
+        name: &str) -> CAresFuture<Result<MXResults, AresError>> {
+        self.submit(|channel, done| channel.search_mx(name, done))
+    }
+
+    /// Search for the NAPTR records associated with `name`.
+    ///
+    /// Like `query_naptr()`, but applies the configured search domains.
+    pub fn search_naptr(
+        &self,
+        name: &str) -> CAresFuture<Result<NAPTRResults, AresError>> {
+        self.submit(|channel, done| channel.search_naptr(name, done))
+    }
+
+    /// Search for the NS records associated with `name`.
+    ///
+    /// Like `query_ns()`, but applies the configured search domains.
+    pub fn search_ns(
+        &self,
+        name: &str) -> CAresFuture<Result<NSResults, AresError>> {
+        self.submit(|channel, done| channel.search_ns(name, done))
+    }
+
+    /// Search for the PTR records associated with `name`.
+    ///
+    /// Like `query_ptr()`, but applies the configured search domains.
+    pub fn search_ptr(
+        &self,
+        name: &str) -> CAresFuture<Result<PTRResults, AresError>> {
+        self.submit(|channel, done| channel.search_ptr(name, done))
+    }
+
+    /// Search for the SRV records associated with `name`.
+    ///
+    /// Like `query_srv()`, but applies the configured search domains.
+    pub fn search_srv(
+        &self,
+        name: &str) -> CAresFuture<Result<SRVResults, AresError>> {
+        self.submit(|channel, done| channel.search_srv(name, done))
+    }
+
+    /// Search for the TXT records associated with `name`.
+    ///
+    /// Like `query_txt()`, but applies the configured search domains.
+    pub fn search_txt(
+        &self,
+        name: &str) -> CAresFuture<Result<TXTResults, AresError>> {
+        self.submit(|channel, done| channel.search_txt(name, done))
+    }
+
+    /// Search for the SOA records associated with `name`.
+    ///
+    /// Like `query_soa()`, but applies the configured search domains.
+    pub fn search_soa(
+        &self,
+        name: &str) -> CAresFuture<Result<SOAResult, AresError>> {
+        self.submit(|channel, done| channel.search_soa(name, done))
+    }
+
+    /// Search for the CAA records associated with `name`.
+    ///
+    /// Like `query_caa()`, but applies the configured search domains.
+    pub fn search_caa(
+        &self,
+        name: &str) -> CAresFuture<Result<CAAResults, AresError>> {
+        self.submit(|channel, done| channel.search_caa(name, done))
+    }
+
+    /// Search for the TLSA records associated with `name`.
+    ///
+    /// Like `query_tlsa()`, but applies the configured search domains.
+    pub fn search_tlsa(
+        &self,
+        name: &str) -> CAresFuture<Result<TLSAResults, AresError>> {
+        self.submit(|channel, done| channel.search_tlsa(name, done))
+    }
+
+    /// Search for the SSHFP records associated with `name`.
+    ///
+    /// Like `query_sshfp()`, but applies the configured search domains.
+    pub fn search_sshfp(
+        &self,
+        name: &str) -> CAresFuture<Result<SSHFPResults, AresError>> {
+        self.submit(|channel, done| channel.search_sshfp(name, done))
+    }
+
+    /// Search for the SVCB records associated with `name`.
+    ///
+    /// Like `query_svcb()`, but applies the configured search domains.
+    pub fn search_svcb(
+        &self,
+        name: &str) -> CAresFuture<Result<SVCBResults, AresError>> {
+        self.submit(|channel, done| channel.search_svcb(name, done))
+    }
+
+    /// Search for the HTTPS records associated with `name`.
+    ///
+    /// Like `query_https()`, but applies the configured search domains.
+    pub fn search_https(
+        &self,
+        name: &str) -> CAresFuture<Result<SVCBResults, AresError>> {
+        self.submit(|channel, done| channel.search_https(name, done))
+    }
+
+    /// Search for the URI records associated with `name`.
+    ///
+    /// Like `query_uri()`, but applies the configured search domains.
+    pub fn search_uri(
+        &self,
+        name: &str) -> CAresFuture<Result<URIResults, AresError>> {
+        self.submit(|channel, done| channel.search_uri(name, done))
+    }
+
+    /// Search for the HINFO records associated with `name`.
+    ///
+    /// Like `query_hinfo()`, but applies the configured search domains.
+    pub fn search_hinfo(
+        &self,
+        name: &str) -> CAresFuture<Result<HINFOResults, AresError>> {
+        self.submit(|channel, done| channel.search_hinfo(name, done))
+    }
+
+    /// Search for the LOC records associated with `name`.
+    ///
+    /// Like `query_loc()`, but applies the configured search domains.
+    pub fn search_loc(
+        &self,
+        name: &str) -> CAresFuture<Result<LOCResults, AresError>> {
+        self.submit(|channel, done| channel.search_loc(name, done))
+    }
+
+    /// Search for the RP records associated with `name`.
+    ///
+    /// Like `query_rp()`, but applies the configured search domains.
+    pub fn search_rp(
+        &self,
+        name: &str) -> CAresFuture<Result<RPResults, AresError>> {
+        self.submit(|channel, done| channel.search_rp(name, done))
+    }
+
+    /// Look up the records of type `query_type` and class `dns_class`
+    /// associated with `name`.  The future completes with the raw DNS
+    /// response, as for `Channel::query_raw()`.
+    pub fn query_raw(
+        &self,
+        name: &str,
+        dns_class: u16,
+        query_type: u16) -> CAresFuture<Result<Vec<u8>, AresError>> {
+        self.submit(|channel, done| {
+            channel.query_raw(name, dns_class, query_type, move |result| {
+                done(result.map(|response| response.to_vec()))
+            })
+        })
+    }
+
+    /// Search for the records of type `query_type` and class `dns_class`
+    /// associated with `name`.
+    ///
+    /// Like `query_raw()`, but applies the configured search domains.
+    pub fn search_raw(
+        &self,
+        name: &str,
+        dns_class: u16,
+        query_type: u16) -> CAresFuture<Result<Vec<u8>, AresError>> {
+        self.submit(|channel, done| {
+            channel.search_raw(name, dns_class, query_type, move |result| {
+                done(result.map(|response| response.to_vec()))
+            })
+        })
+    }
+
+    /// Send a DNS query message, such as one built by `create_query()`.  The
+    /// future completes with the raw DNS response.
+    pub fn send_raw(
+        &self,
+        query: &[u8]) -> CAresFuture<Result<Vec<u8>, AresError>> {
+        self.submit(|channel, done| {
+            channel.send_raw(query, move |result| {
+                done(result.map(|response| response.to_vec()))
+            })
+        })
+    }
+
+    /// Perform a host query by address.
+    pub fn get_host_by_address(
+        &self,
+        address: &IpAddr)
+        -> CAresFuture<Result<OwnedHostResults, AresError>> {
+        self.submit(|channel, done| {
+            channel.get_host_by_address(address, move |result| {
+                done(result.map(|results| results.to_owned()))
+            })
+        })
+    }
+
+    /// Perform a host query by name.
+    pub fn get_host_by_name(
+        &self,
+        name: &str,
+        family: AddressFamily)
+        -> CAresFuture<Result<OwnedHostResults, AresError>> {
+        self.submit(|channel, done| {
+            channel.get_host_by_name(name, family, move |result| {
+                done(result.map(|results| results.to_owned()))
+            })
+        })
+    }
+
+    /// Address-to-nodename translation in protocol-independent manner.
+    pub fn get_name_info(
+        &self,
+        address: &SocketAddr,
+        flags: NIFlags)
+        -> CAresFuture<Result<OwnedNameInfoResult, AresError>> {
+        self.submit(|channel, done| {
+            channel.get_name_info(address, flags, move |result| {
+                done(result.map(|result| result.to_owned()))
+            })
+        })
+    }
+
+    // Make a query using `query`, which is passed the `Channel` and a
+    // callback to complete with the result.
+    fn submit<R, Q>(&self, query: Q) -> CAresFuture<Result<R, AresError>>
+        where R: Send + 'static,
+              Q: FnOnce(&mut Channel,
+                        Box<FnOnce(Result<R, AresError>) + Send>)
+                 -> QueryHandle {
+        let (future, done) = CAresFuture::new();
+        {
+            let mut channel = self.channel.lock().unwrap();
+            let handle = query(&mut channel, done);
+            if let Some(timeout) = *self.query_timeout.lock().unwrap() {
+                let deadline = std::time::Instant::now() + timeout;
+                channel.set_query_deadline(&handle, deadline);
+            }
+        }
+
+        // Prod the driver, since the new query may have changed when c-ares
+        // next needs to handle timeouts.
+        let _ = self.events.send(DriverEvent::Wakeup);
+        future
+    }
+}
+
+// Messages for the driver task.
+enum DriverEvent {
+    // c-ares is now interested in reading and / or writing on this socket.
+    // If it's interested in neither, the socket should be forgotten.
+    SocketState(io::RawFd, bool, bool),
+
+    // Something has changed, re-evaluate when the next timeout is due.
+    Wakeup,
+}
+
+// A socket that c-ares is interested in.
+struct Socket {
+    async_fd: AsyncFd<io::RawFd>,
+    readable: bool,
+    writable: bool,
+}
+
+// The task that drives a `TokioResolver`'s `Channel`.
+struct Driver {
+    channel: Arc<Mutex<Channel>>,
+    events: mpsc::UnboundedReceiver<DriverEvent>,
+    shutdown: oneshot::Receiver<()>,
+    sockets: HashMap<io::RawFd, Socket>,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl Driver {
+    // Apply any changes in socket state that c-ares has told us about.
+    // Returns false if the resolver has gone away.
+    fn handle_events(&mut self, cx: &mut Context) -> bool {
+        loop {
+            match self.events.poll_recv(cx) {
+                Poll::Ready(Some(DriverEvent::SocketState(fd, r, w))) => {
+                    self.update_socket(fd, r, w);
+                },
+                Poll::Ready(Some(DriverEvent::Wakeup)) => (),
+                Poll::Ready(None) => return false,
+                Poll::Pending => return true,
+            }
+        }
+    }
+
+    fn update_socket(
+        &mut self,
+        fd: io::RawFd,
+        readable: bool,
+        writable: bool) {
+        if !readable && !writable {
+            self.sockets.remove(&fd);
+            return
+        }
+        if let Some(socket) = self.sockets.get_mut(&fd) {
+            socket.readable = readable;
+            socket.writable = writable;
+            return
+        }
+        let interest = Interest::READABLE | Interest::WRITABLE;
+        if let Ok(async_fd) = AsyncFd::with_interest(fd, interest) {
+            let socket = Socket {
+                async_fd: async_fd,
+                readable: readable,
+                writable: writable,
+            };
+            self.sockets.insert(fd, socket);
+        }
+    }
+
+    // Returns `(fd, readable, writable)` for each socket that Tokio says is
+    // ready, and that c-ares is interested in.
+    fn ready_sockets(
+        &mut self,
+        cx: &mut Context) -> Vec<(io::RawFd, bool, bool)> {
+        let mut ready = Vec::new();
+        for (&fd, socket) in self.sockets.iter() {
+            let readable = socket.readable &&
+                socket.async_fd.poll_read_ready(cx).is_ready();
+            let writable = socket.writable &&
+                socket.async_fd.poll_write_ready(cx).is_ready();
+            if readable || writable {
+                ready.push((fd, readable, writable));
+            }
+        }
+        ready
+    }
+
+    // Tokio's readiness is edge-triggered, and c-ares doesn't promise to
+    // drain the sockets that it's told about.  So once c-ares has had a
+    // chance to process a socket, ask the operating system whether it's still
+    // ready - and only clear Tokio's readiness if it isn't.
+    fn clear_readiness(
+        &mut self,
+        cx: &mut Context,
+        processed: &[(io::RawFd, bool, bool)]) {
+        let still_ready = poll_fds(processed, Some(Duration::from_secs(0)));
+        for &(fd, readable, writable) in processed {
+            let socket = match self.sockets.get(&fd) {
+                Some(socket) => socket,
+                None => continue,
+            };
+            let (still_readable, still_writable) = still_ready
+                .iter()
+                .find(|&&(ready_fd, _, _)| ready_fd == fd)
+                .map(|&(_, r, w)| (r, w))
+                .unwrap_or((false, false));
+            if readable && !(still_readable && socket.readable) {
+                if let Poll::Ready(Ok(mut guard)) =
+                    socket.async_fd.poll_read_ready(cx) {
+                    guard.clear_ready();
+                }
+            }
+            if writable && !(still_writable && socket.writable) {
+                if let Poll::Ready(Ok(mut guard)) =
+                    socket.async_fd.poll_write_ready(cx) {
+                    guard.clear_ready();
+                }
+            }
+        }
+    }
+}
+
+impl Future for Driver {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let this = &mut *self;
+        if Pin::new(&mut this.shutdown).poll(cx).is_ready() {
+            return Poll::Ready(())
+        }
+
+        // A panicking handler is re-raised by `process_fd()`.  Letting it
+        // unwind would poison the channel and end this task, leaving every
+        // other query hanging - so catch it, and carry on.  The future
+        // belonging to that handler has already been completed.
+        //
+        // A socket that keeps receiving could keep us here forever, so after
+        // a while, yield - and ask to be polled again straight away.
+        for _ in 0..MAX_PASSES {
+            if !this.handle_events(cx) {
+                return Poll::Ready(())
+            }
+
+            // Tell c-ares about any sockets that are ready.
+            let ready = this.ready_sockets(cx);
+            if !ready.is_empty() {
+                {
+                    let mut channel = this.channel.lock().unwrap();
+                    for &(fd, readable, writable) in &ready {
+                        let read_fd = if readable { fd } else { INVALID_FD };
+                        let write_fd = if writable { fd } else { INVALID_FD };
+                        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+                            channel.process_fd(read_fd, write_fd)
+                        }));
+                    }
+                }
+                if !this.handle_events(cx) {
+                    return Poll::Ready(())
+                }
+                this.clear_readiness(cx, &ready);
+                continue
+            }
+
+            // Arrange to wake up when c-ares next needs to handle timeouts.
+            let timeout = this.channel.lock().unwrap().timeout(None);
+            if let Some(timeout) = timeout {
+                this.sleep.as_mut().reset(Instant::now() + timeout);
+                if this.sleep.as_mut().poll(cx).is_ready() {
+                    let mut channel = this.channel.lock().unwrap();
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+                        channel.process_timeouts()
+                    }));
+                    continue
+                }
+            }
+            return Poll::Pending
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
@@ -1,85 +1,104 @@
 extern crate c_ares_sys;
 extern crate libc;
 
-use std::ffi::CStr;
-use std::marker::PhantomData;
+use std::borrow::Cow;
 use std::mem;
-use std::ptr;
 use std::slice;
-use std::str;
 
 use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
 use utils::ares_error;
 
 /// The result of a successful TXT lookup.
 pub struct TXTResults {
-    txt_reply: *mut c_ares_sys::Struct_ares_txt_reply,
-    phantom: PhantomData<c_ares_sys::Struct_ares_txt_reply>,
+    txt_records: Vec<TXTRecord>,
 }
 
 /// The contents of a single TXT record.
 pub struct TXTResult<'a> {
-    txt_reply: *const c_ares_sys::Struct_ares_txt_reply,
-    phantom: PhantomData<&'a c_ares_sys::Struct_ares_txt_reply>,
+    // A single result - reference into a `TXTResults`.
+    txt_record: &'a TXTRecord,
+}
+
+/// An owned copy of a single TXT record, as returned by
+/// `TXTResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedTXTResult {
+    /// The text, with any invalid UTF-8 replaced by U+FFFD.
+    pub text: String,
+
+    /// The character-strings that make up the record, exactly as received.
+    pub strings: Vec<Vec<u8>>,
+}
+
+// c-ares flattens a TXT record into NUL-terminated strings, losing embedded
+// NULs and the boundaries between character-strings, so we parse the
+// records ourselves.
+struct TXTRecord {
+    strings: Vec<Vec<u8>>,
+    bytes: Vec<u8>,
 }
 
 impl TXTResults {
     /// Obtain a `TXTResults` from the response to a TXT lookup.
     pub fn parse_from(data: &[u8]) -> Result<TXTResults, AresError> {
-        let mut txt_reply: *mut c_ares_sys::Struct_ares_txt_reply =
-            ptr::null_mut();
-        let parse_status = unsafe {
-            c_ares_sys::ares_parse_txt_reply(
-                data.as_ptr(),
-                data.len() as libc::c_int,
-                &mut txt_reply)
-        };
-        if parse_status != c_ares_sys::ARES_SUCCESS {
-            Err(ares_error(parse_status))
-        } else {
-            let result = TXTResults::new(txt_reply);
-            Ok(result)
-        }
-    }
-    fn new(txt_reply: *mut c_ares_sys::Struct_ares_txt_reply) -> TXTResults {
-        TXTResults {
-            txt_reply: txt_reply,
-            phantom: PhantomData,
+        let message = Message::parse(data)?;
+        let mut txt_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::TXT)? {
+            let mut reader = Reader::new(rr.rdata());
+            let mut strings = Vec::new();
+            let mut bytes = Vec::new();
+            while !reader.is_empty() {
+                let string = reader.read_string()?;
+                bytes.extend_from_slice(string);
+                strings.push(string.to_vec());
+            }
+            let txt_record = TXTRecord {
+                strings: strings,
+                bytes: bytes,
+            };
+            txt_records.push(txt_record);
         }
+        let results = TXTResults {
+            txt_records: txt_records,
+        };
+        Ok(results)
     }
 
     /// Returns an iterator over the `TXTResult` values in this `TXTResults`.
     pub fn iter(&self) -> TXTResultsIterator {
         TXTResultsIterator {
-            next: self.txt_reply,
-            phantom: PhantomData,
+            next: self.txt_records.iter(),
         }
     }
+
+    /// Returns owned copies of the `TXTResult` values in this `TXTResults`,
+    /// which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedTXTResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
 }
 
 pub struct TXTResultsIterator<'a> {
-    next: *const c_ares_sys::Struct_ares_txt_reply,
-    phantom: PhantomData<&'a c_ares_sys::Struct_ares_txt_reply>,
+    next: slice::Iter<'a, TXTRecord>,
 }
 
 impl<'a> Iterator for TXTResultsIterator<'a> {
     type Item = TXTResult<'a>;
     fn next(&mut self) -> Option<Self::Item> {
-        let txt_reply = self.next;
-        if txt_reply.is_null() {
-            None
-        } else {
-            unsafe {
-                self.next = (*txt_reply).next;
+        self.next.next().map(|txt_record| {
+            TXTResult {
+                txt_record: txt_record,
             }
-            let txt_result = TXTResult {
-                txt_reply: txt_reply,
-                phantom: PhantomData,
-            };
-            Some(txt_result)
-        }
+        })
     }
 }
+
 impl<'a> IntoIterator for &'a TXTResults {
     type Item = TXTResult<'a>;
     type IntoIter = TXTResultsIterator<'a>;
@@ -89,27 +108,31 @@ impl<'a> IntoIterator for &'a TXTResults {
     }
 }
 
-impl Drop for TXTResults {
-    fn drop(&mut self) {
-        unsafe {
-            c_ares_sys::ares_free_data(self.txt_reply as *mut libc::c_void);
-        }
+impl<'a> TXTResult<'a> {
+    /// Returns the text in this `TXTResult`: its character-strings joined
+    /// together, with any invalid UTF-8 replaced by U+FFFD.
+    pub fn text(&self) -> Cow<str> {
+        String::from_utf8_lossy(&self.txt_record.bytes)
     }
-}
 
-unsafe impl Send for TXTResults { }
-unsafe impl Sync for TXTResults { }
-unsafe impl<'a> Send for TXTResult<'a> { }
-unsafe impl<'a> Sync for TXTResult<'a> { }
-unsafe impl<'a> Send for TXTResultsIterator<'a> { }
-unsafe impl<'a> Sync for TXTResultsIterator<'a> { }
+    /// Returns the data in this `TXTResult`: its character-strings joined
+    /// together, exactly as received.
+    pub fn bytes(&self) -> &[u8] {
+        &self.txt_record.bytes
+    }
 
-impl<'a> TXTResult<'a> {
-    /// Returns the text in this `TXTResult`.
-    pub fn text(&self) -> &str {
-        unsafe {
-            let c_str = CStr::from_ptr((*self.txt_reply).txt as *const i8);
-            str::from_utf8_unchecked(c_str.to_bytes())
+    /// Returns the character-strings that make up this `TXTResult`, exactly
+    /// as received.  Long values - DKIM keys, for instance - are typically
+    /// split across several of these.
+    pub fn strings(&self) -> &[Vec<u8>] {
+        &self.txt_record.strings
+    }
+
+    /// Returns an owned copy of this `TXTResult`.
+    pub fn to_owned(&self) -> OwnedTXTResult {
+        OwnedTXTResult {
+            text: self.text().into_owned(),
+            strings: self.strings().to_vec(),
         }
     }
 }
@@ -121,12 +144,73 @@ pub unsafe extern "C" fn query_txt_callback<F>(
     abuf: *mut libc::c_uchar,
     alen: libc::c_int)
     where F: FnOnce(Result<TXTResults, AresError>) + 'static {
-    let handler: Box<F> = mem::transmute(arg);
-    let result = if status != c_ares_sys::ARES_SUCCESS {
-        Err(ares_error(status))
-    } else {
-        let data = slice::from_raw_parts(abuf, alen as usize);
-        TXTResults::parse_from(data)
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            TXTResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_rejects_overlong_rdata,
+        assert_rejects_rdata,
+        parse_test_response,
     };
-    handler(result);
+    use types::QueryType;
+    use super::TXTResults;
+
+    #[test]
+    fn parses_records() {
+        let results = parse_test_response(
+            TXTResults::parse_from,
+            QueryType::TXT,
+            &[b"\x05hello", b"\x05hello\x00\x06 world"])
+            .unwrap();
+        let results: Vec<_> = results.iter().collect();
+        assert_eq!(results[0].text(), "hello");
+        assert_eq!(results[0].strings(), &[b"hello".to_vec()]);
+        assert_eq!(results[1].text(), "hello world");
+        assert_eq!(results[1].bytes(), b"hello world");
+        assert_eq!(
+            results[1].strings(),
+            &[b"hello".to_vec(), Vec::new(), b" world".to_vec()]);
+    }
+
+    #[test]
+    fn keeps_binary_data() {
+        let results = parse_test_response(
+            TXTResults::parse_from,
+            QueryType::TXT,
+            &[b"\x04a\x00\xffb"]).unwrap();
+        let result = results.iter().next().unwrap();
+        assert_eq!(result.bytes(), b"a\x00\xffb");
+        assert_eq!(result.text(), "a\u{0}\u{fffd}b");
+
+        let owned = result.to_owned();
+        assert_eq!(owned.text, "a\u{0}\u{fffd}b");
+        assert_eq!(owned.strings, vec![b"a\x00\xffb".to_vec()]);
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(TXTResults::parse_from, QueryType::TXT, &[
+            b"\x05hell",
+            b"\x05hello\x03ab"]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        let rdata: &[u8] = b"\x05hello";
+        assert_rejects_overlong_rdata(
+            TXTResults::parse_from,
+            QueryType::TXT,
+            rdata);
+    }
 }
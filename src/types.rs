@@ -22,6 +22,7 @@ pub enum AddressFamily {
 }
 
 /// An IP address, either an IPv4 or an IPv6 address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum IpAddr {
     /// An IPv4 address.
     V4(Ipv4Addr),
@@ -39,21 +40,104 @@ pub struct hostent {
     pub h_addr_list: *mut *mut libc::c_char,
 }
 
-// See arpa/nameser.h
+/// DNS record types.
+///
+/// See arpa/nameser.h, and the IANA registry of DNS parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum QueryType {
+    /// IPv4 address.
     A = 1,
+
+    /// Authoritative name server.
     NS = 2,
+
+    /// Canonical name.
     CNAME = 5,
+
+    /// Start of authority.
     SOA = 6,
+
+    /// Domain name pointer.
     PTR = 12,
+
+    /// Host information.
+    HINFO = 13,
+
+    /// Mail exchange.
     MX = 15,
+
+    /// Text strings.
     TXT = 16,
+
+    /// Responsible person.
+    RP = 17,
+
+    /// IPv6 address.
     AAAA = 28,
+
+    /// Location information.
+    LOC = 29,
+
+    /// Service location.
     SRV = 33,
+
+    /// Naming authority pointer.
     NAPTR = 35,
+
+    /// EDNS pseudo-record.
+    OPT = 41,
+
+    /// Delegation signer.
+    DS = 43,
+
+    /// SSH key fingerprint.
+    SSHFP = 44,
+
+    /// DNSSEC signature.
+    RRSIG = 46,
+
+    /// Next secure record.
+    NSEC = 47,
+
+    /// DNS key.
+    DNSKEY = 48,
+
+    /// Next secure record, version 3.
+    NSEC3 = 50,
+
+    /// TLSA certificate association.
+    TLSA = 52,
+
+    /// Service binding.
+    SVCB = 64,
+
+    /// HTTPS service binding.
+    HTTPS = 65,
+
+    /// All records.
+    ANY = 255,
+
+    /// Uniform resource identifier.
+    URI = 256,
+
+    /// Certification authority authorization.
+    CAA = 257,
 }
 
-// See arpa/nameser.h
+/// DNS classes.
+///
+/// See arpa/nameser.h.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DnsClass {
-   IN = 1,
+    /// Internet.
+    IN = 1,
+
+    /// Chaos.
+    CHAOS = 3,
+
+    /// Hesiod.
+    HS = 4,
+
+    /// Any class.
+    ANY = 255,
 }
@@ -0,0 +1,32 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{
+    self,
+    AssertUnwindSafe,
+};
+
+// Panics must not unwind through c-ares, so the callbacks that we give it
+// catch them and park them here.  Callbacks only run during a call into
+// c-ares, so the `Channel` that made that call collects them straight
+// afterwards, on the same thread.
+thread_local! {
+    static PANIC: RefCell<Option<Box<Any + Send>>> = RefCell::new(None);
+}
+
+// Calls `f`, catching any panic.  If more than one panic is caught before
+// `take()` is called, only the first is kept.
+pub fn catch<F>(f: F) where F: FnOnce() {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+        PANIC.with(|panic| {
+            let mut panic = panic.borrow_mut();
+            if panic.is_none() {
+                *panic = Some(payload);
+            }
+        });
+    }
+}
+
+// Returns the panic caught by `catch()`, if there is one.
+pub fn take() -> Option<Box<Any + Send>> {
+    PANIC.with(|panic| panic.borrow_mut().take())
+}
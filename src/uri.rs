@@ -0,0 +1,216 @@
+extern crate c_ares_sys;
+extern crate libc;
+
+use std::mem;
+use std::slice;
+use std::str;
+
+use error::AresError;
+use message::{
+    Message,
+    Reader,
+};
+use types::QueryType;
+use unwind;
+use utils::ares_error;
+
+/// The result of a successful URI lookup.
+pub struct URIResults {
+    uri_records: Vec<URIRecord>,
+}
+
+/// The contents of a single URI record.
+pub struct URIResult<'a> {
+    // A single result - reference into a `URIResults`.
+    uri_record: &'a URIRecord,
+}
+
+/// An owned copy of a single URI record, as returned by
+/// `URIResult::to_owned()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedURIResult {
+    /// The priority.
+    pub priority: u16,
+
+    /// The weight.
+    pub weight: u16,
+
+    /// The URI.
+    pub target: String,
+}
+
+// A URI record, as parsed from the response.
+struct URIRecord {
+    priority: u16,
+    weight: u16,
+    target: String,
+}
+
+impl URIResults {
+    /// Obtain a `URIResults` from the response to a URI lookup.
+    pub fn parse_from(data: &[u8]) -> Result<URIResults, AresError> {
+        let message = Message::parse(data)?;
+        let mut uri_records = Vec::new();
+        for rr in message.answers_of_type(QueryType::URI)? {
+            let mut reader = Reader::new(rr.rdata());
+            let priority = reader.read_u16()?;
+            let weight = reader.read_u16()?;
+            let target = str::from_utf8(reader.read_rest())
+                .map_err(|_| AresError::EBADRESP)?;
+            let uri_record = URIRecord {
+                priority: priority,
+                weight: weight,
+                target: target.to_owned(),
+            };
+            uri_records.push(uri_record);
+        }
+        let results = URIResults {
+            uri_records: uri_records,
+        };
+        Ok(results)
+    }
+
+    /// Returns an iterator over the `URIResult` values in this
+    /// `URIResults`.
+    pub fn iter(&self) -> URIResultsIterator {
+        URIResultsIterator {
+            next: self.uri_records.iter(),
+        }
+    }
+
+    /// Returns owned copies of the `URIResult` values in this
+    /// `URIResults`, which can outlive it.
+    pub fn to_owned(&self) -> Vec<OwnedURIResult> {
+        self.iter().map(|result| result.to_owned()).collect()
+    }
+}
+
+pub struct URIResultsIterator<'a> {
+    next: slice::Iter<'a, URIRecord>,
+}
+
+impl<'a> Iterator for URIResultsIterator<'a> {
+    type Item = URIResult<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.next().map(|uri_record| {
+            URIResult {
+                uri_record: uri_record,
+            }
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a URIResults {
+    type Item = URIResult<'a>;
+    type IntoIter = URIResultsIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> URIResult<'a> {
+    /// Returns the priority from this `URIResult`.  Lower values are
+    /// preferred.
+    pub fn priority(&self) -> u16 {
+        self.uri_record.priority
+    }
+
+    /// Returns the weight from this `URIResult`, for choosing between
+    /// results with the same priority.
+    pub fn weight(&self) -> u16 {
+        self.uri_record.weight
+    }
+
+    /// Returns the URI from this `URIResult`.
+    pub fn target(&self) -> &str {
+        &self.uri_record.target
+    }
+
+    /// Returns an owned copy of this `URIResult`.
+    pub fn to_owned(&self) -> OwnedURIResult {
+        OwnedURIResult {
+            priority: self.priority(),
+            weight: self.weight(),
+            target: self.target().to_owned(),
+        }
+    }
+}
+
+pub unsafe extern "C" fn query_uri_callback<F>(
+    arg: *mut libc::c_void,
+    status: libc::c_int,
+    _timeouts: libc::c_int,
+    abuf: *mut libc::c_uchar,
+    alen: libc::c_int)
+    where F: FnOnce(Result<URIResults, AresError>) + 'static {
+    unwind::catch(|| {
+        let handler: Box<F> = mem::transmute(arg);
+        let result = if status != c_ares_sys::ARES_SUCCESS {
+            Err(ares_error(status))
+        } else {
+            let data = slice::from_raw_parts(abuf, alen as usize);
+            URIResults::parse_from(data)
+        };
+        handler(result);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use message::{
+        assert_rejects_overlong_rdata,
+        assert_rejects_rdata,
+        parse_test_response,
+    };
+    use types::QueryType;
+    use super::{
+        OwnedURIResult,
+        URIResults,
+    };
+
+    #[test]
+    fn parses_records() {
+        let results = parse_test_response(
+            URIResults::parse_from,
+            QueryType::URI,
+            &[b"\x00\x0a\x00\x01ftp://ftp1.example.com/public"])
+            .unwrap();
+        let result = results.iter().next().unwrap();
+        assert_eq!(result.priority(), 10);
+        assert_eq!(result.weight(), 1);
+        assert_eq!(result.target(), "ftp://ftp1.example.com/public");
+        let expected = vec![
+            OwnedURIResult {
+                priority: 10,
+                weight: 1,
+                target: "ftp://ftp1.example.com/public".to_owned(),
+            },
+        ];
+        assert_eq!(results.to_owned(), expected);
+    }
+
+    #[test]
+    fn rejects_bad_targets() {
+        assert_rejects_rdata(
+            URIResults::parse_from,
+            QueryType::URI,
+            &[b"\x00\x0a\x00\x01\xff"]);
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        assert_rejects_rdata(URIResults::parse_from, QueryType::URI, &[
+            b"",
+            b"\x00\x0a\x00"]);
+    }
+
+    #[test]
+    fn rejects_overlong_rdata() {
+        let rdata: &[u8] = b"\x00\x0a\x00\x01http://example.com/";
+        assert_rejects_overlong_rdata(
+            URIResults::parse_from,
+            QueryType::URI,
+            rdata);
+    }
+}
@@ -2,7 +2,9 @@ extern crate c_ares_sys;
 extern crate libc;
 
 use error::AresError;
+use message::check_name;
 use types::AddressFamily;
+use std::ffi::CString;
 use std::mem;
 use std::net::{
     Ipv4Addr,
@@ -43,6 +45,19 @@ pub fn ares_error(code: libc::c_int) -> AresError {
     }
 }
 
+// Converts a name that we've been asked to look up into a C string.  We
+// check it up front: c-ares can't see past an interior NUL, and we'd rather
+// report a name that's too long here than have it fail later.
+pub fn to_c_name(name: &str) -> Result<CString, AresError> {
+    check_name(name)?;
+    CString::new(name).map_err(|_| AresError::EBADNAME)
+}
+
+// Converts a string to a `CString`, failing if it contains a NUL.
+pub fn to_c_string(s: &str) -> Result<CString, AresError> {
+    CString::new(s).map_err(|_| AresError::EBADSTR)
+}
+
 // Converts an address family into a more strongly typed AddressFamily.
 pub fn address_family(family: libc::c_int) -> Option<AddressFamily> {
     match family {
@@ -64,11 +79,7 @@ pub fn ipv4_as_in_addr(ipv4: &Ipv4Addr) -> libc::in_addr {
 
 // Gets an in6_addr from an IP64Addr.
 pub fn ipv6_as_in6_addr(ipv6: &Ipv6Addr) -> libc::in6_addr {
-    let mut segments = ipv6.segments();
-    for segment in segments.iter_mut() {
-        *segment = segment.to_be();
-    }
-    libc::in6_addr { s6_addr: segments }
+    libc::in6_addr { s6_addr: ipv6.octets() }
 }
 
 // Gets a sockaddr_in from a SocketAddr4.